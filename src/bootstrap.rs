@@ -0,0 +1,415 @@
+//! Bootstrap confidence intervals for statistics without a closed-form
+//! variance.
+//!
+//! Each arm's observations are resampled with replacement, independently of
+//! the other arms, and the statistic is recomputed on every resample. The
+//! spread of those replicates gives the interval. Resample `i` always draws
+//! from its own random stream, so a seed fixes the result exactly, whether
+//! the resamples run on one thread or many.
+
+use crate::distributions::normal;
+use crate::error::{Error, Result};
+use crate::random::Rng;
+
+/// The number of groups each arm is split into for the jackknife behind
+/// [`BootstrapMethod::Bca`].
+const JACKKNIFE_GROUPS: usize = 200;
+
+/// How the bootstrap replicates are turned into an interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BootstrapMethod {
+    /// The quantiles of the replicates.
+    Percentile,
+    /// The percentile interval reflected about the estimate, also called the
+    /// basic or pivotal interval.
+    Basic,
+    /// Efron's bias-corrected and accelerated interval, which adjusts the
+    /// percentiles for bias and skewness. The share of replicates below the
+    /// estimate, which sets the bias correction, is kept within half a
+    /// replicate of 0 and 1 so that the correction stays finite when every
+    /// replicate falls on one side.
+    #[default]
+    Bca,
+    /// The bootstrap-t interval, which studentizes each replicate with a
+    /// standard error from a nested bootstrap.
+    Studentized,
+}
+
+/// Settings for [`bootstrap`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BootstrapOptions {
+    /// The number of resamples.
+    pub resamples: usize,
+    /// The confidence level of the interval.
+    pub confidence: f64,
+    /// How the interval is formed.
+    pub method: BootstrapMethod,
+    /// The seed of the random number generator.
+    pub seed: u64,
+    /// The number of threads to run resamples on; `0` uses every available
+    /// core.
+    pub threads: usize,
+    /// The number of nested resamples behind each standard error of
+    /// [`BootstrapMethod::Studentized`].
+    pub inner_resamples: usize,
+}
+
+impl Default for BootstrapOptions {
+    fn default() -> Self {
+        BootstrapOptions {
+            resamples: 10_000,
+            confidence: 0.95,
+            method: BootstrapMethod::Bca,
+            seed: 0,
+            threads: 1,
+            inner_resamples: 50,
+        }
+    }
+}
+
+impl BootstrapOptions {
+    /// Sets the number of resamples.
+    pub fn resamples(mut self, resamples: usize) -> Self {
+        self.resamples = resamples;
+        self
+    }
+
+    /// Sets the confidence level.
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    /// Sets the interval method.
+    pub fn method(mut self, method: BootstrapMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Sets the seed.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the number of threads; `0` uses every available core.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Sets the number of nested resamples for the studentized interval.
+    pub fn inner_resamples(mut self, inner_resamples: usize) -> Self {
+        self.inner_resamples = inner_resamples;
+        self
+    }
+
+    /// Checks that the confidence lies in `(0, 1)` and that there are at
+    /// least two resamples, and two nested resamples for the studentized
+    /// interval.
+    pub fn validate(&self) -> Result<()> {
+        if !(self.confidence > 0.0 && self.confidence < 1.0) {
+            return Err(Error::InvalidParameter {
+                name: "confidence",
+                value: self.confidence,
+            });
+        }
+        if self.resamples < 2 {
+            return Err(Error::InvalidParameter {
+                name: "resamples",
+                value: self.resamples as f64,
+            });
+        }
+        if self.method == BootstrapMethod::Studentized && self.inner_resamples < 2 {
+            return Err(Error::InvalidParameter {
+                name: "inner_resamples",
+                value: self.inner_resamples as f64,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of [`bootstrap`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BootstrapResult {
+    /// The statistic on the original samples.
+    pub estimate: f64,
+    /// The standard deviation of the replicates.
+    pub standard_error: f64,
+    /// The mean of the replicates minus the estimate.
+    pub bias: f64,
+    /// Lower bound of the confidence interval.
+    pub ci_low: f64,
+    /// Upper bound of the confidence interval.
+    pub ci_high: f64,
+    /// The replicates of the statistic, in ascending order.
+    pub replicates: Vec<f64>,
+}
+
+/// Bootstraps a statistic of one or more arms.
+///
+/// `statistic` receives one slice per arm, in the order of `samples`, and
+/// may compute anything from them: a difference of means, a ratio of
+/// medians, a trimmed mean. It must be `Sync` so that resamples can run in
+/// parallel.
+///
+/// # Errors
+///
+/// Returns [`Error::TooFewGroups`] if there are no arms,
+/// [`Error::InsufficientSample`] if an arm is empty,
+/// [`Error::InvalidParameter`] for invalid options or if the statistic is not
+/// finite on the samples or on a resample, and [`Error::ZeroVariance`] if the
+/// studentized interval meets a resample whose standard error is zero.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::bootstrap::{bootstrap, BootstrapMethod, BootstrapOptions};
+///
+/// let a = [12.0, 0.0, 3.5, 0.0, 48.0, 7.2, 0.0, 15.0, 2.2, 9.9];
+/// let b = [20.0, 4.5, 0.0, 31.0, 55.0, 9.0, 12.5, 0.0, 18.0, 26.0];
+/// let mean = |x: &[f64]| x.iter().sum::<f64>() / x.len() as f64;
+/// let options = BootstrapOptions::default().resamples(2000).seed(7);
+/// let result = bootstrap(&[&a, &b], |s| mean(s[1]) - mean(s[0]), &options).unwrap();
+/// assert!(result.ci_low < result.estimate && result.estimate < result.ci_high);
+///
+/// // The seed fixes the result however many threads are used.
+/// let parallel = bootstrap(&[&a, &b], |s| mean(s[1]) - mean(s[0]), &options.threads(4)).unwrap();
+/// assert_eq!(result, parallel);
+///
+/// // The percentile interval holds the middle of the replicates, and the
+/// // basic interval reflects it about the estimate.
+/// let percentile = options.method(BootstrapMethod::Percentile);
+/// let percentile = bootstrap(&[&a, &b], |s| mean(s[1]) - mean(s[0]), &percentile).unwrap();
+/// let basic = options.method(BootstrapMethod::Basic);
+/// let basic = bootstrap(&[&a, &b], |s| mean(s[1]) - mean(s[0]), &basic).unwrap();
+/// assert!(percentile.replicates[0] < percentile.ci_low);
+/// assert!(percentile.ci_high < percentile.replicates[1999]);
+/// assert!((basic.ci_low - (2.0 * basic.estimate - percentile.ci_high)).abs() < 1e-12);
+/// assert!((basic.ci_high - (2.0 * basic.estimate - percentile.ci_low)).abs() < 1e-12);
+///
+/// // The studentized interval scales the bootstrap-t quantiles by the
+/// // standard error, so it also brackets the estimate.
+/// let studentized = options.resamples(500).method(BootstrapMethod::Studentized);
+/// let studentized = bootstrap(&[&a, &b], |s| mean(s[1]) - mean(s[0]), &studentized).unwrap();
+/// assert!(studentized.ci_low < studentized.estimate);
+/// assert!(studentized.estimate < studentized.ci_high);
+///
+/// // Every replicate of the number of distinct values falls below the
+/// // estimate, and the BCa interval still stays finite.
+/// let distinct: Vec<f64> = (0..20).map(f64::from).collect();
+/// let count = |s: &[&[f64]]| {
+///     let mut values = s[0].to_vec();
+///     values.sort_by(f64::total_cmp);
+///     values.dedup();
+///     values.len() as f64
+/// };
+/// let result = bootstrap(&[&distinct], count, &options).unwrap();
+/// assert!(result.replicates.iter().all(|&r| r < result.estimate));
+/// assert!(result.ci_low.is_finite() && result.ci_high.is_finite());
+/// ```
+pub fn bootstrap<F>(
+    samples: &[&[f64]],
+    statistic: F,
+    options: &BootstrapOptions,
+) -> Result<BootstrapResult>
+where
+    F: Fn(&[&[f64]]) -> f64 + Sync,
+{
+    options.validate()?;
+    if samples.is_empty() {
+        return Err(Error::TooFewGroups {
+            required: 1,
+            actual: 0,
+        });
+    }
+    if samples.iter().any(|s| s.is_empty()) {
+        return Err(Error::InsufficientSample {
+            required: 1,
+            actual: 0,
+        });
+    }
+    let estimate = finite(statistic(samples))?;
+
+    let studentized = options.method == BootstrapMethod::Studentized;
+    let draws = run(options, |rng, buffers| {
+        resample(samples, buffers, rng);
+        let views: Vec<&[f64]> = buffers.iter().map(Vec::as_slice).collect();
+        let replicate = finite(statistic(&views))?;
+        if !studentized {
+            return Ok((replicate, 0.0));
+        }
+        let mut inner = vec![Vec::new(); samples.len()];
+        let inner_replicates = (0..options.inner_resamples)
+            .map(|_| {
+                resample(&views, &mut inner, rng);
+                let inner_views: Vec<&[f64]> = inner.iter().map(Vec::as_slice).collect();
+                finite(statistic(&inner_views))
+            })
+            .collect::<Result<Vec<f64>>>()?;
+        let (_, sd) = mean_sd(&inner_replicates);
+        if sd <= 0.0 {
+            return Err(Error::ZeroVariance);
+        }
+        Ok((replicate, (replicate - estimate) / sd))
+    })?;
+
+    let mut replicates: Vec<f64> = draws.iter().map(|d| d.0).collect();
+    replicates.sort_by(f64::total_cmp);
+    let (mean, standard_error) = mean_sd(&replicates);
+    let tail = (1.0 - options.confidence) / 2.0;
+    let (ci_low, ci_high) = match options.method {
+        BootstrapMethod::Percentile => (
+            percentile(&replicates, tail),
+            percentile(&replicates, 1.0 - tail),
+        ),
+        BootstrapMethod::Basic => (
+            2.0 * estimate - percentile(&replicates, 1.0 - tail),
+            2.0 * estimate - percentile(&replicates, tail),
+        ),
+        BootstrapMethod::Bca => {
+            let below = replicates.iter().filter(|&&r| r < estimate).count() as f64;
+            let equal = replicates.iter().filter(|&&r| r == estimate).count() as f64;
+            let n = replicates.len() as f64;
+            let z0 = normal::quantile(((below + 0.5 * equal) / n).clamp(0.5 / n, 1.0 - 0.5 / n));
+            let a = acceleration(samples, &statistic)?;
+            let adjusted = |p: f64| {
+                let z = z0 + normal::quantile(p);
+                normal::cdf(z0 + z / (1.0 - a * z))
+            };
+            (
+                percentile(&replicates, adjusted(tail)),
+                percentile(&replicates, adjusted(1.0 - tail)),
+            )
+        }
+        BootstrapMethod::Studentized => {
+            let mut t: Vec<f64> = draws.iter().map(|d| d.1).collect();
+            t.sort_by(f64::total_cmp);
+            (
+                estimate - percentile(&t, 1.0 - tail) * standard_error,
+                estimate - percentile(&t, tail) * standard_error,
+            )
+        }
+    };
+
+    Ok(BootstrapResult {
+        estimate,
+        standard_error,
+        bias: mean - estimate,
+        ci_low,
+        ci_high,
+        replicates,
+    })
+}
+
+/// Runs `draw` once per resample, each with its own random stream, and
+/// returns the results in resample order.
+fn run<T, D>(options: &BootstrapOptions, draw: D) -> Result<Vec<T>>
+where
+    T: Send,
+    D: Fn(&mut Rng, &mut Vec<Vec<f64>>) -> Result<T> + Sync,
+{
+    let threads = match options.threads {
+        0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        n => n,
+    }
+    .min(options.resamples);
+    let chunk = options.resamples.div_ceil(threads);
+    let work = |start: usize| -> Result<Vec<T>> {
+        let mut buffers = Vec::new();
+        (start..(start + chunk).min(options.resamples))
+            .map(|i| draw(&mut Rng::with_stream(options.seed, i as u64), &mut buffers))
+            .collect()
+    };
+    if threads == 1 {
+        return work(0);
+    }
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|t| scope.spawn(move || work(t * chunk)))
+            .collect();
+        let mut results = Vec::with_capacity(options.resamples);
+        for handle in handles {
+            results.extend(handle.join().expect("bootstrap worker panicked")?);
+        }
+        Ok(results)
+    })
+}
+
+/// Fills `buffers` with a resample of each arm.
+fn resample(samples: &[&[f64]], buffers: &mut Vec<Vec<f64>>, rng: &mut Rng) {
+    buffers.resize(samples.len(), Vec::new());
+    for (sample, buffer) in samples.iter().zip(buffers.iter_mut()) {
+        buffer.clear();
+        buffer.extend((0..sample.len()).map(|_| sample[rng.below(sample.len() as u64) as usize]));
+    }
+}
+
+/// The acceleration of the BCa interval, from a grouped jackknife that
+/// leaves out one group of observations of one arm at a time.
+fn acceleration<F>(samples: &[&[f64]], statistic: &F) -> Result<f64>
+where
+    F: Fn(&[&[f64]]) -> f64,
+{
+    let mut cubes = 0.0;
+    let mut squares = 0.0;
+    for (k, sample) in samples.iter().enumerate() {
+        let groups = sample.len().min(JACKKNIFE_GROUPS);
+        if groups < 2 {
+            continue;
+        }
+        let values = (0..groups)
+            .map(|g| {
+                let kept: Vec<f64> = sample
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| i % groups != g)
+                    .map(|(_, &x)| x)
+                    .collect();
+                let mut views = samples.to_vec();
+                views[k] = &kept;
+                finite(statistic(&views))
+            })
+            .collect::<Result<Vec<f64>>>()?;
+        let n = groups as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        for v in values {
+            let influence = (n - 1.0) * (mean - v) / n;
+            cubes += influence.powi(3);
+            squares += influence * influence;
+        }
+    }
+    Ok(if squares > 0.0 {
+        cubes / (6.0 * squares.powf(1.5))
+    } else {
+        0.0
+    })
+}
+
+/// The `p` quantile of sorted values, interpolating linearly between order
+/// statistics.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let position = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let below = position.floor() as usize;
+    let above = position.ceil() as usize;
+    sorted[below] + (position - below as f64) * (sorted[above] - sorted[below])
+}
+
+fn mean_sd(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance.sqrt())
+}
+
+fn finite(value: f64) -> Result<f64> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(Error::InvalidParameter {
+            name: "statistic",
+            value,
+        })
+    }
+}
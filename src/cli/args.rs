@@ -0,0 +1,107 @@
+//! Parsing of `--name value` flags.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A problem that ends the program with a message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CliError(pub String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<statistical_computing::Error> for CliError {
+    fn from(error: statistical_computing::Error) -> Self {
+        CliError(error.to_string())
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(error: std::io::Error) -> Self {
+        CliError(error.to_string())
+    }
+}
+
+/// The flags of one subcommand, each of which must be read before
+/// [`Args::finish`].
+#[derive(Debug, Clone, Default)]
+pub struct Args {
+    values: BTreeMap<String, String>,
+}
+
+impl Args {
+    /// Collects `--name value` and `--name=value` pairs.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, CliError> {
+        let mut values = BTreeMap::new();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                return Err(CliError(format!("unexpected argument '{}'", arg)));
+            };
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| CliError(format!("--{} needs a value", flag)))?;
+                    (flag.to_string(), value)
+                }
+            };
+            if values.insert(name.clone(), value).is_some() {
+                return Err(CliError(format!("--{} is given more than once", name)));
+            }
+        }
+        Ok(Args { values })
+    }
+
+    /// Takes the raw value of a flag, if it was given.
+    pub fn text(&mut self, name: &str) -> Option<String> {
+        self.values.remove(name)
+    }
+
+    /// Takes and parses the value of a flag, if it was given.
+    pub fn get<T: FromStr>(&mut self, name: &str) -> Result<Option<T>, CliError> {
+        self.text(name).map(|value| parse(name, &value)).transpose()
+    }
+
+    /// Takes and parses the value of a flag, or returns `default`.
+    pub fn value<T: FromStr>(&mut self, name: &str, default: T) -> Result<T, CliError> {
+        Ok(self.get(name)?.unwrap_or(default))
+    }
+
+    /// Takes and parses the value of a flag that must be given.
+    pub fn require<T: FromStr>(&mut self, name: &str) -> Result<T, CliError> {
+        self.get(name)?
+            .ok_or_else(|| CliError(format!("--{} is required", name)))
+    }
+
+    /// Takes and parses a comma-separated list.
+    pub fn list<T: FromStr>(&mut self, name: &str) -> Result<Option<Vec<T>>, CliError> {
+        self.text(name)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|item| parse(name, item.trim()))
+                    .collect()
+            })
+            .transpose()
+    }
+
+    /// Rejects any flag that was given but never read.
+    pub fn finish(self) -> Result<(), CliError> {
+        match self.values.keys().next() {
+            Some(name) => Err(CliError(format!("unknown flag --{}", name))),
+            None => Ok(()),
+        }
+    }
+}
+
+fn parse<T: FromStr>(name: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError(format!("invalid value '{}' for --{}", value, name)))
+}
@@ -0,0 +1,898 @@
+//! The `statistical_computing` command-line interface.
+//!
+//! Each subcommand reads its inputs from flags or from a file, runs one of
+//! the library's analyses and prints a [`Report`] as text or JSON.
+
+mod args;
+mod report;
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+
+use statistical_computing::conversion::{ab_conversion_test_counts, Alternative, Arm, TestOptions};
+use statistical_computing::csv::{read_events, CsvOptions};
+use statistical_computing::exact::fisher_exact;
+use statistical_computing::power::{
+    minimum_detectable_effect, sample_size, Effect, Planner, PowerOptions, SampleSize,
+};
+use statistical_computing::random::Rng;
+use statistical_computing::srm::{srm_test, SrmPolicy};
+use statistical_computing::two_sample::welch_t_test;
+use statistical_computing::{IntervalMethod, Variant};
+
+use args::Args;
+pub use args::CliError;
+use report::{Format, Report};
+
+/// The malformed lines of an event log listed individually.
+const MALFORMED_SHOWN: usize = 3;
+
+const USAGE: &str = "\
+Usage: statistical_computing <command> [flags]
+
+Commands:
+  analyze    Compare the conversions of two variants
+  plan       Find the sample size for an effect, or the effect for a sample size
+  srm        Check that traffic was split as intended
+  simulate   Estimate the power of a design by simulating experiments
+
+Every command accepts --format text|json (default text).
+Run 'statistical_computing <command> --help' for its flags.
+";
+
+const ANALYZE: &str = "\
+Usage: statistical_computing analyze (--a X/N --b X/N | --input FILE | --events FILE) [flags]
+
+  --a X/N              Conversions and units of version A, such as 200/1000
+  --b X/N              Conversions and units of version B
+  --input FILE         Read both arms from FILE, or from stdin if FILE is '-';
+                       one line per arm: [label] conversions units
+  --events FILE        Read a CSV event log with the columns user_id, variant,
+                       converted and revenue from FILE, or from stdin if FILE
+                       is '-'; revenue per user is tested as well
+  --delimiter C        Field separator of the event log (default ',')
+  --test z|fisher      The two-proportion z-test (default) or Fisher's exact test
+  --alpha P            Significance level (default 0.05)
+  --confidence P       Confidence level of the intervals (default 0.95)
+  --alternative two-sided|greater|less
+  --interval wald|newcombe|agresti-caffo|miettinen-nurminen
+  --allocation R       Intended units of B per unit of A (default 1)
+  --srm ignore|flag|block
+";
+
+const PLAN: &str = "\
+Usage: statistical_computing plan --baseline P (--mde D | --lift L | --per-arm N) [flags]
+
+  --baseline P         Conversion rate of the control
+  --mde D              Absolute difference in rates to detect, such as 0.01
+  --lift L             Relative lift to detect, such as 0.05 for 5%
+  --per-arm N          Units of A available; report the detectable effect
+  --daily-traffic N    Units per day across both arms; report the duration
+  --alpha P            Significance level (default 0.05)
+  --power P            Probability of detecting the effect (default 0.8)
+  --ratio R            Units of B per unit of A (default 1)
+  --alternative two-sided|greater|less
+";
+
+const SRM: &str = "\
+Usage: statistical_computing srm (--counts N,N,... | --input FILE) [flags]
+
+  --counts N,N,...     Units in each arm
+  --input FILE         Read the counts from FILE, or from stdin if FILE is '-';
+                       one line per arm: [label] units
+  --weights W,W,...    Intended share of each arm (default even)
+  --alpha P            Threshold for flagging a mismatch (default 0.001)
+";
+
+const SIMULATE: &str = "\
+Usage: statistical_computing simulate --baseline P (--mde D | --lift L) --per-arm N [flags]
+
+  --baseline P         Conversion rate of A
+  --mde D              Difference of B's rate from A's (default 0, an A/A test)
+  --lift L             Relative lift of B over A
+  --per-arm N          Units in each arm
+  --runs N             Number of simulated experiments (default 1000)
+  --seed N             Seed of the random number generator (default 0)
+  --alpha P            Significance level (default 0.05)
+  --alternative two-sided|greater|less
+";
+
+/// Runs the command in `args`, which excludes the program name, and
+/// returns what to print. Inputs given as `-` are read from `stdin`.
+pub fn run(
+    args: impl IntoIterator<Item = String>,
+    stdin: &mut dyn BufRead,
+) -> Result<String, CliError> {
+    let mut args = args.into_iter();
+    let Some(command) = args.next() else {
+        return Ok(USAGE.to_string());
+    };
+    let rest: Vec<String> = args.collect();
+    let help = match command.as_str() {
+        "analyze" => ANALYZE,
+        "plan" => PLAN,
+        "srm" => SRM,
+        "simulate" => SIMULATE,
+        "help" | "--help" | "-h" => return Ok(USAGE.to_string()),
+        other => {
+            return Err(CliError(format!(
+                "unknown command '{}'\n\n{}",
+                other, USAGE
+            )))
+        }
+    };
+    if rest.iter().any(|arg| arg == "--help" || arg == "-h") {
+        return Ok(help.to_string());
+    }
+    let mut args = Args::parse(rest)?;
+    let format = match args.text("format").as_deref() {
+        None | Some("text") => Format::Text,
+        Some("json") => Format::Json,
+        Some(other) => return Err(invalid("format", other)),
+    };
+    let report = match command.as_str() {
+        "analyze" => analyze(&mut args, stdin)?,
+        "plan" => plan(&mut args)?,
+        "srm" => srm(&mut args, stdin)?,
+        _ => simulate(&mut args)?,
+    };
+    args.finish()?;
+    Ok(report.render(format))
+}
+
+fn analyze(args: &mut Args, stdin: &mut dyn BufRead) -> Result<Report, CliError> {
+    let mut report = Report::default();
+    let mut revenue = None;
+    let (labels, a, b) = match (args.text("input"), args.text("events")) {
+        (Some(path), None) => {
+            let text = read(&path, stdin)?;
+            let rows = rows(&text);
+            if rows.len() != 2 {
+                return Err(CliError(format!(
+                    "{} holds {}, but analyze compares two",
+                    path,
+                    plural(rows.len(), "arm")
+                )));
+            }
+            let mut labels = Vec::new();
+            let mut arms = Vec::new();
+            for (line, fields) in rows {
+                let (label, counts) = split_label(&fields, 2, line)?;
+                labels.push(
+                    label
+                        .unwrap_or(if arms.is_empty() { "A" } else { "B" })
+                        .to_string(),
+                );
+                let successes = field(counts[0], line)?;
+                let trials = field(counts[1], line)?;
+                arms.push(Arm::new(successes, trials));
+            }
+            (labels, arms[0], arms[1])
+        }
+        (None, Some(path)) => {
+            let mut options = CsvOptions::default();
+            if let Some(delimiter) = args.get("delimiter")? {
+                options = options.delimiter(delimiter);
+            }
+            let events = if path == "-" {
+                read_events(stdin, &options)?
+            } else {
+                let file = File::open(&path)
+                    .map_err(|e| CliError(format!("cannot read {}: {}", path, e)))?;
+                read_events(BufReader::new(file), &options)?
+            };
+            if events.variants.len() != 2 {
+                return Err(CliError(format!(
+                    "{} holds {}, but analyze compares two",
+                    path,
+                    plural(events.variants.len(), "variant")
+                )));
+            }
+            for row in events.malformed.iter().take(MALFORMED_SHOWN) {
+                report.line(format!("Skipped line {}: {}.", row.line, row.reason));
+            }
+            if events.malformed.len() > MALFORMED_SHOWN {
+                report.line(format!(
+                    "Skipped {} more malformed lines.",
+                    events.malformed.len() - MALFORMED_SHOWN
+                ));
+            }
+            if events.crossover_users > 0 {
+                report.line(format!(
+                    "Left out users who saw both variants: {}.",
+                    events.crossover_users
+                ));
+            }
+            report
+                .field("rows", events.rows)
+                .field("duplicate_rows", events.duplicate_rows)
+                .field("crossover_users", events.crossover_users)
+                .field("malformed_rows", events.malformed.len() as u64);
+            let (a, b) = (&events.variants[0], &events.variants[1]);
+            revenue = a.revenue.zip(b.revenue);
+            (vec![a.name.clone(), b.name.clone()], a.arm, b.arm)
+        }
+        (None, None) => (
+            vec!["A".to_string(), "B".to_string()],
+            arm(&args.require::<String>("a")?)?,
+            arm(&args.require::<String>("b")?)?,
+        ),
+        (Some(_), Some(_)) => {
+            return Err(CliError("give --input or --events, not both".to_string()))
+        }
+    };
+    let mut options = TestOptions::default()
+        .alpha(args.value("alpha", 0.05)?)
+        .confidence(args.value("confidence", 0.95)?)
+        .allocation(args.value("allocation", 1.0)?);
+    if let Some(alternative) = args.text("alternative") {
+        options = options.alternative(parse_alternative(&alternative)?);
+    }
+    if let Some(interval) = args.text("interval") {
+        options = options.interval(match interval.as_str() {
+            "wald" => IntervalMethod::Wald,
+            "newcombe" => IntervalMethod::Newcombe,
+            "agresti-caffo" => IntervalMethod::AgrestiCaffo,
+            "miettinen-nurminen" => IntervalMethod::MiettinenNurminen,
+            other => return Err(invalid("interval", other)),
+        });
+    }
+    if let Some(srm) = args.text("srm") {
+        options = options.srm(match srm.as_str() {
+            "ignore" => SrmPolicy::Ignore,
+            "flag" => SrmPolicy::Flag,
+            "block" => SrmPolicy::Block,
+            other => return Err(invalid("srm", other)),
+        });
+    }
+    let winner_label = |winner: Option<Variant>| {
+        winner.map(|variant| match variant {
+            Variant::A => labels[0].clone(),
+            Variant::B => labels[1].clone(),
+        })
+    };
+
+    match args.text("test").as_deref() {
+        None | Some("z") => {
+            let result = ab_conversion_test_counts(a, b, &options)?;
+            if let Some(srm) = result.srm.filter(|srm| srm.mismatch) {
+                report.line(format!(
+                    "Warning: the traffic split looks wrong (sample ratio mismatch, p = {:.2e}), so the result may not be trustworthy.",
+                    srm.p_value
+                ));
+            }
+            // The relative bounds of A against B invert those of B against A.
+            let invert = |lift: f64| 1.0 / (1.0 + lift) - 1.0;
+            let bounds = match result.winner {
+                Some(Variant::A) => Some((
+                    -result.ci_high,
+                    -result.ci_low,
+                    invert(result.relative_ci_high),
+                    invert(result.relative_ci_low),
+                )),
+                Some(Variant::B) => Some((
+                    result.ci_low,
+                    result.ci_high,
+                    result.relative_ci_low,
+                    result.relative_ci_high,
+                )),
+                None => None,
+            };
+            match (winner_label(result.winner), bounds) {
+                (Some(name), Some((lo, hi, relative_lo, relative_hi))) => {
+                    report.line(format!(
+                        "Version {} is the winner!\nThe increase in conversion rates is likely between {:.2}% and {:.2}%.",
+                        name,
+                        lo * 100.,
+                        hi * 100.
+                    ));
+                    report.line(format!(
+                        "That is {:.1}% to {:.1}% more conversions than the other version.",
+                        relative_lo * 100.,
+                        relative_hi * 100.
+                    ));
+                }
+                _ => {
+                    report.line(format!(
+                        "No statistically significant difference was found (p = {:.4}).",
+                        result.p_value
+                    ));
+                }
+            }
+            report
+                .field("test", "two-proportion z-test")
+                .field("winner", winner_label(result.winner))
+                .field("rate_a", a.rate())
+                .field("rate_b", b.rate())
+                .field("z", result.z)
+                .field("p_value", result.p_value)
+                .field("difference", result.difference)
+                .field("ci_low", result.ci_low)
+                .field("ci_high", result.ci_high)
+                .field("relative_lift", result.relative_lift)
+                .field("relative_ci_low", result.relative_ci_low)
+                .field("relative_ci_high", result.relative_ci_high)
+                .field("odds_ratio", result.odds_ratio)
+                .field("odds_ratio_ci_low", result.odds_ratio_ci_low)
+                .field("odds_ratio_ci_high", result.odds_ratio_ci_high)
+                .field(
+                    "srm",
+                    result.srm.map(|srm| {
+                        let mut report = Report::default();
+                        report
+                            .field("statistic", srm.statistic)
+                            .field("p_value", srm.p_value)
+                            .field("mismatch", srm.mismatch);
+                        report
+                    }),
+                );
+        }
+        Some("fisher") => {
+            let result = fisher_exact(a, b, &options)?;
+            match winner_label(result.winner) {
+                Some(name) => report.line(format!("Version {} is the winner!", name)),
+                None => report.line(format!(
+                    "No statistically significant difference was found (p = {:.4}).",
+                    result.p_value
+                )),
+            };
+            report
+                .field("test", "Fisher's exact test")
+                .field("winner", winner_label(result.winner))
+                .field("rate_a", a.rate())
+                .field("rate_b", b.rate())
+                .field("p_value", result.p_value)
+                .field("odds_ratio", result.odds_ratio)
+                .field("odds_ratio_ci_low", result.ci_low)
+                .field("odds_ratio_ci_high", result.ci_high);
+        }
+        Some(other) => return Err(invalid("test", other)),
+    }
+
+    if let Some((revenue_a, revenue_b)) = revenue {
+        // Revenue that never varies, such as none at all, cannot be tested.
+        match welch_t_test(revenue_a, revenue_b, &options.into()) {
+            Ok(result) => {
+                report.line(format!(
+                    "Revenue per user is {:.2} for {} and {:.2} for {} (p = {:.4}).",
+                    revenue_a.mean, labels[0], revenue_b.mean, labels[1], result.p_value
+                ));
+                let mut fields = Report::default();
+                fields
+                    .field("test", "Welch's t-test")
+                    .field("winner", winner_label(result.winner))
+                    .field("mean_a", revenue_a.mean)
+                    .field("mean_b", revenue_b.mean)
+                    .field("p_value", result.p_value)
+                    .field("difference", result.difference)
+                    .field("ci_low", result.ci_low)
+                    .field("ci_high", result.ci_high);
+                report.field("revenue", fields);
+            }
+            Err(statistical_computing::Error::ZeroVariance) => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(report)
+}
+
+fn plan(args: &mut Args) -> Result<Report, CliError> {
+    let baseline: f64 = args.require("baseline")?;
+    let mut options = PowerOptions::default()
+        .alpha(args.value("alpha", 0.05)?)
+        .power(args.value("power", 0.8)?)
+        .ratio(args.value("ratio", 1.0)?);
+    if let Some(alternative) = args.text("alternative") {
+        options = options.alternative(parse_alternative(&alternative)?);
+    }
+    let effect = effect(args)?;
+    let per_arm: Option<u64> = args.get("per-arm")?;
+    let daily_traffic: Option<f64> = args.get("daily-traffic")?;
+
+    let mut report = Report::default();
+    report.field("baseline", baseline);
+    match (effect, per_arm) {
+        (Some(effect), None) => {
+            let size = sample_size(baseline, effect, &options)?;
+            report.line(format!(
+                "Detecting a change from {:.2}% to {:.2}% needs {} units in A and {} in B ({} in total).",
+                baseline * 100.,
+                effect.treatment_rate(baseline) * 100.,
+                size.a,
+                size.b,
+                size.total()
+            ));
+            report
+                .field("treatment_rate", effect.treatment_rate(baseline))
+                .field("per_arm_a", size.a)
+                .field("per_arm_b", size.b)
+                .field("total", size.total());
+            if let Some(traffic) = daily_traffic {
+                let days = Planner::new(baseline, traffic)
+                    .weights(vec![1.0, options.ratio])
+                    .options(options)
+                    .days_to_detect(effect)?;
+                report.line(format!(
+                    "At {} units a day that takes {} days.",
+                    traffic, days
+                ));
+                report.field("days", days);
+            }
+        }
+        (None, Some(a)) => {
+            let b = (a as f64 * options.ratio).ceil() as u64;
+            let mde = minimum_detectable_effect(baseline, SampleSize { a, b }, &options)?;
+            match mde {
+                Some(mde) => report.line(format!(
+                    "With {} units in A and {} in B, the smallest detectable difference is {:.2} percentage points ({:.1}% relative).",
+                    a,
+                    b,
+                    mde * 100.,
+                    mde / baseline * 100.
+                )),
+                None => report.line(format!(
+                    "With {} units in A and {} in B, no effect reaches the requested power.",
+                    a, b
+                )),
+            };
+            report
+                .field("per_arm_a", a)
+                .field("per_arm_b", b)
+                .field("absolute_mde", mde)
+                .field("relative_mde", mde.map(|mde| mde / baseline));
+            if daily_traffic.is_some() {
+                return Err(CliError(
+                    "--daily-traffic needs an effect, not --per-arm".to_string(),
+                ));
+            }
+        }
+        _ => {
+            return Err(CliError(
+                "plan needs exactly one of --mde, --lift and --per-arm".to_string(),
+            ))
+        }
+    }
+    Ok(report)
+}
+
+fn srm(args: &mut Args, stdin: &mut dyn BufRead) -> Result<Report, CliError> {
+    let (labels, counts) = match (args.text("input"), args.list::<u64>("counts")?) {
+        (Some(path), None) => {
+            let mut labels = Vec::new();
+            let mut counts = Vec::new();
+            let text = read(&path, stdin)?;
+            for (line, fields) in rows(&text) {
+                let (label, count) = split_label(&fields, 1, line)?;
+                labels.push(label.map(str::to_string));
+                counts.push(field(count[0], line)?);
+            }
+            (labels, counts)
+        }
+        (None, Some(counts)) => (vec![None; counts.len()], counts),
+        _ => {
+            return Err(CliError(
+                "srm needs exactly one of --counts and --input".to_string(),
+            ))
+        }
+    };
+    let weights = args
+        .list::<f64>("weights")?
+        .unwrap_or_else(|| vec![1.0; counts.len()]);
+    let result = srm_test(&counts, &weights, args.value("alpha", 0.001)?)?;
+
+    let mut report = Report::default();
+    if result.mismatch {
+        report.line(format!(
+            "Sample ratio mismatch: the arm sizes are implausible under the intended split (p = {:.2e}).",
+            result.p_value
+        ));
+    } else {
+        report.line(format!(
+            "The arm sizes are consistent with the intended split (p = {:.4}).",
+            result.p_value
+        ));
+    }
+    if labels.iter().any(Option::is_some) {
+        let labels: Vec<String> = labels
+            .into_iter()
+            .enumerate()
+            .map(|(i, label)| label.unwrap_or_else(|| (i + 1).to_string()))
+            .collect();
+        report.field("arms", labels);
+    }
+    report
+        .field("counts", counts)
+        .field("weights", weights)
+        .field("statistic", result.statistic)
+        .field("df", result.df)
+        .field("p_value", result.p_value)
+        .field("mismatch", result.mismatch);
+    Ok(report)
+}
+
+fn simulate(args: &mut Args) -> Result<Report, CliError> {
+    let baseline: f64 = args.require("baseline")?;
+    let effect = effect(args)?.unwrap_or(Effect::Absolute(0.0));
+    let per_arm: u64 = args.require("per-arm")?;
+    let runs: u64 = args.value("runs", 1000)?;
+    let seed: u64 = args.value("seed", 0)?;
+    let mut options = TestOptions::default()
+        .alpha(args.value("alpha", 0.05)?)
+        .srm(SrmPolicy::Ignore);
+    if let Some(alternative) = args.text("alternative") {
+        options = options.alternative(parse_alternative(&alternative)?);
+    }
+    let treatment = effect.treatment_rate(baseline);
+    for rate in [baseline, treatment] {
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(statistical_computing::Error::InvalidProportion(rate).into());
+        }
+    }
+    if runs == 0 {
+        return Err(invalid("runs", "0"));
+    }
+
+    let mut rng = Rng::new(seed);
+    let mut draw = |rate: f64| Arm::new(rng.binomial(per_arm, rate), per_arm);
+    let (mut wins_a, mut wins_b, mut total_difference) = (0u64, 0u64, 0.0);
+    for _ in 0..runs {
+        let (a, b) = (draw(baseline), draw(treatment));
+        total_difference += b.rate() - a.rate();
+        // Runs in which neither arm varies cannot be tested and count as
+        // finding nothing.
+        match ab_conversion_test_counts(a, b, &options) {
+            Ok(result) => match result.winner {
+                Some(Variant::A) => wins_a += 1,
+                Some(Variant::B) => wins_b += 1,
+                None => {}
+            },
+            Err(statistical_computing::Error::ZeroVariance) => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+    let rejection_rate = (wins_a + wins_b) as f64 / runs as f64;
+
+    let mut report = Report::default();
+    if treatment == baseline {
+        report.line(format!(
+            "With no real difference, {:.1}% of {} simulated experiments found one (the false positive rate).",
+            rejection_rate * 100.,
+            runs
+        ));
+    } else {
+        report.line(format!(
+            "{:.1}% of {} simulated experiments found a significant difference (the power).",
+            rejection_rate * 100.,
+            runs
+        ));
+    }
+    report
+        .field("rate_a", baseline)
+        .field("rate_b", treatment)
+        .field("per_arm", per_arm)
+        .field("runs", runs)
+        .field("seed", seed)
+        .field("rejection_rate", rejection_rate)
+        .field("wins_a", wins_a)
+        .field("wins_b", wins_b)
+        .field("mean_difference", total_difference / runs as f64);
+    Ok(report)
+}
+
+/// The effect given by `--mde` or `--lift`, if either.
+fn effect(args: &mut Args) -> Result<Option<Effect>, CliError> {
+    match (args.get("mde")?, args.get("lift")?) {
+        (Some(mde), None) => Ok(Some(Effect::Absolute(mde))),
+        (None, Some(lift)) => Ok(Some(Effect::Relative(lift))),
+        (None, None) => Ok(None),
+        (Some(_), Some(_)) => Err(CliError("give --mde or --lift, not both".to_string())),
+    }
+}
+
+fn parse_alternative(value: &str) -> Result<Alternative, CliError> {
+    match value {
+        "two-sided" => Ok(Alternative::TwoSided),
+        "greater" => Ok(Alternative::Greater),
+        "less" => Ok(Alternative::Less),
+        other => Err(invalid("alternative", other)),
+    }
+}
+
+/// Parses conversions and units written as `X/N`.
+fn arm(value: &str) -> Result<Arm, CliError> {
+    let parsed = value
+        .split_once('/')
+        .and_then(|(x, n)| Some(Arm::new(x.trim().parse().ok()?, n.trim().parse().ok()?)));
+    parsed.ok_or_else(|| {
+        CliError(format!(
+            "invalid arm '{}', expected conversions/units such as 200/1000",
+            value
+        ))
+    })
+}
+
+fn invalid(flag: &str, value: &str) -> CliError {
+    CliError(format!("invalid value '{}' for --{}", value, flag))
+}
+
+/// `count` followed by `noun`, with an `s` unless the count is one.
+fn plural(count: usize, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+/// Reads a file, or `stdin` for `-`.
+fn read(path: &str, stdin: &mut dyn BufRead) -> Result<String, CliError> {
+    if path == "-" {
+        let mut text = String::new();
+        stdin.read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path).map_err(|e| CliError(format!("cannot read {}: {}", path, e)))
+    }
+}
+
+/// The fields of each line that is neither blank nor a `#` comment, split
+/// on commas and whitespace, with 1-based line numbers.
+fn rows(text: &str) -> Vec<(usize, Vec<&str>)> {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap_or("")))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            let fields = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|field| !field.is_empty())
+                .collect();
+            (number, fields)
+        })
+        .collect()
+}
+
+/// Splits an optional leading label from `values` numeric fields.
+fn split_label<'a>(
+    fields: &[&'a str],
+    values: usize,
+    line: usize,
+) -> Result<(Option<&'a str>, Vec<&'a str>), CliError> {
+    match fields.len() {
+        n if n == values => Ok((None, fields.to_vec())),
+        n if n == values + 1 => Ok((Some(fields[0]), fields[1..].to_vec())),
+        n => Err(CliError(format!(
+            "line {}: expected {} values, found {}",
+            line, values, n
+        ))),
+    }
+}
+
+fn field(value: &str, line: usize) -> Result<u64, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError(format!("line {}: '{}' is not a count", line, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cli(args: &str, stdin: &str) -> Result<String, CliError> {
+        run(
+            args.split_whitespace().map(str::to_string),
+            &mut stdin.as_bytes(),
+        )
+    }
+
+    fn error(args: &str, stdin: &str) -> String {
+        cli(args, stdin).unwrap_err().0
+    }
+
+    #[test]
+    fn analyze_reports_the_winner() {
+        let output = cli("analyze --a 100/1000 --b 150/1000", "").unwrap();
+        assert!(output.starts_with("Version B is the winner!\n"));
+        assert!(output.contains("\nwinner:              B\n"));
+
+        let output = cli("analyze --a 100/1000 --b 150/1000 --format json", "").unwrap();
+        assert!(output.starts_with("{\"test\":\"two-proportion z-test\",\"winner\":\"B\","));
+        assert!(output.ends_with("\"mismatch\":false}}\n"));
+    }
+
+    #[test]
+    fn json_writes_non_finite_numbers_as_null() {
+        let output = cli("analyze --test fisher --a 0/10 --b 5/10 --format json", "").unwrap();
+        assert!(output.contains("\"odds_ratio\":null,"));
+        assert!(output.contains("\"odds_ratio_ci_high\":null}"));
+    }
+
+    #[test]
+    fn json_escapes_strings() {
+        let output = cli("srm --input - --format json", "a\"b\\c 5000\nd 5000\n").unwrap();
+        assert!(output.starts_with("{\"arms\":[\"a\\\"b\\\\c\",\"d\"],"));
+    }
+
+    #[test]
+    fn input_rows_take_labels_comments_and_commas() {
+        let input = "# control first\nctl 10 100 # after a comment\n\ntrt, 20, 100\n";
+        let output = cli("analyze --input - --format json", input).unwrap();
+        assert!(output.contains("\"winner\":\"trt\","));
+        assert!(output.contains("\"rate_a\":0.1,\"rate_b\":0.2,"));
+
+        let output = cli("analyze --input -", "10 100\n20 100\n").unwrap();
+        assert!(output.contains("\nwinner:              B\n"));
+    }
+
+    #[test]
+    fn input_rows_reject_malformed_lines() {
+        assert_eq!(
+            error("analyze --input -", "ctl 10 100\ntrt x 100\n"),
+            "line 2: 'x' is not a count"
+        );
+        assert_eq!(
+            error("analyze --input -", "ctl 10 100\ntrt 20 100 extra\n"),
+            "line 2: expected 2 values, found 4"
+        );
+        assert_eq!(
+            error("analyze --input -", "ctl 10 100\n"),
+            "- holds 1 arm, but analyze compares two"
+        );
+        assert_eq!(
+            error("analyze --input -", "ctl 10 100\ntrt 20 100\nalt 30 100\n"),
+            "- holds 3 arms, but analyze compares two"
+        );
+        assert_eq!(
+            error("srm --input -", "a 10\nb -3\n"),
+            "line 2: '-3' is not a count"
+        );
+    }
+
+    const EVENTS: &str = "\
+user_id;variant;converted;revenue\r
+u1;control;0;\r
+u2;control;1;\"1,5\"\r
+u2;control;1;5.0\r
+u3;control;0;\r
+u4;control;0;\r
+u5;control;1;12.5\r
+u6;control;0;\r
+u6;treatment;1;3\r
+u7;treatment;1;30.0\r
+u8;treatment;yes;18.0\r
+u9;treatment;0;\r
+u10;treatment;maybe;\r
+u11;treatment;1;25.0\r
+u12;treatment;1;22.0\r
+u13;treatment;0;\r
+";
+
+    #[test]
+    fn events_are_read_from_stdin() {
+        let output = cli("analyze --events - --delimiter ;", EVENTS).unwrap();
+        assert!(output.starts_with(
+            "Skipped line 3: '1,5' is not a revenue amount.\n\
+             Skipped line 13: 'maybe' is not a conversion flag.\n\
+             Left out users who saw both variants: 1.\n"
+        ));
+        assert!(output.contains("Revenue per user is 3.50 for control and 15.83 for treatment"));
+
+        let output = cli("analyze --events - --delimiter ; --format json", EVENTS).unwrap();
+        assert!(output.starts_with(
+            "{\"rows\":13,\"duplicate_rows\":1,\"crossover_users\":1,\"malformed_rows\":2,"
+        ));
+        assert!(output.contains("\"rate_a\":0.4,\"rate_b\":0.6666666666666666,"));
+        assert!(output
+            .contains("\"revenue\":{\"test\":\"Welch's t-test\",\"winner\":null,\"mean_a\":3.5,"));
+    }
+
+    #[test]
+    fn events_are_read_from_a_file() {
+        let path = std::env::temp_dir().join(format!("events-{}.csv", std::process::id()));
+        fs::write(&path, EVENTS.replace(';', "\t")).unwrap();
+        let args = [
+            "analyze",
+            "--events",
+            path.to_str().unwrap(),
+            "--delimiter",
+            "\t",
+        ];
+        let output = run(args.map(str::to_string), &mut "".as_bytes());
+        fs::remove_file(&path).unwrap();
+        assert!(output.unwrap().contains("\nrows:                13\n"));
+
+        assert!(error("analyze --events /nonexistent/events.csv", "").starts_with("cannot read"));
+    }
+
+    #[test]
+    fn events_need_two_variants() {
+        let events = "user_id,variant,converted,revenue\nu1,a,1,\nu2,b,0,\nu3,c,1,\n";
+        assert_eq!(
+            error("analyze --events -", events),
+            "- holds 3 variants, but analyze compares two"
+        );
+        assert_eq!(
+            error(
+                "analyze --events -",
+                "user_id,variant,converted,revenue\nu1,a,1,\nu2,a,0,\n"
+            ),
+            "- holds 1 variant, but analyze compares two"
+        );
+        assert_eq!(
+            error("analyze --events - --input -", events),
+            "give --input or --events, not both"
+        );
+        assert_eq!(
+            error("analyze --events - --delimiter ;;", events),
+            "invalid value ';;' for --delimiter"
+        );
+    }
+
+    #[test]
+    fn plan_finds_the_sample_size_and_duration() {
+        let output = cli(
+            "plan --baseline 0.1 --lift 0.1 --daily-traffic 2000 --format json",
+            "",
+        )
+        .unwrap();
+        assert!(
+            output.contains("\"per_arm_a\":14751,\"per_arm_b\":14751,\"total\":29502,\"days\":15}")
+        );
+        assert_eq!(
+            error("plan --baseline 0.1", ""),
+            "plan needs exactly one of --mde, --lift and --per-arm"
+        );
+    }
+
+    #[test]
+    fn srm_flags_a_mismatch() {
+        let output = cli("srm --counts 5000,5300 --alpha 0.01", "").unwrap();
+        assert!(output.starts_with("Sample ratio mismatch:"));
+        assert!(output.contains("\nmismatch:   yes\n"));
+
+        let output = cli("srm --counts 5000,5300", "").unwrap();
+        assert!(output.starts_with("The arm sizes are consistent"));
+    }
+
+    #[test]
+    fn simulate_is_fixed_by_the_seed() {
+        let args = "simulate --baseline 0.1 --per-arm 1000 --runs 200 --seed 3 --format json";
+        let output = cli(args, "").unwrap();
+        assert_eq!(output, cli(args, "").unwrap());
+        assert!(output.contains("\"rejection_rate\":0.04,\"wins_a\":4,\"wins_b\":4,"));
+        assert_eq!(
+            error("simulate --baseline 0.1 --per-arm 1000 --runs 0", ""),
+            "invalid value '0' for --runs"
+        );
+    }
+
+    #[test]
+    fn flags_are_checked() {
+        assert_eq!(
+            error("srm --counts 1,2 --counts 3,4", ""),
+            "--counts is given more than once"
+        );
+        assert_eq!(
+            error("srm --counts=1,2 --counts 3,4", ""),
+            "--counts is given more than once"
+        );
+        assert_eq!(
+            error("srm --counts 1,2 --colour red", ""),
+            "unknown flag --colour"
+        );
+        assert_eq!(error("srm --counts", ""), "--counts needs a value");
+        assert_eq!(error("srm counts", ""), "unexpected argument 'counts'");
+        assert_eq!(error("plan --lift 0.1", ""), "--baseline is required");
+        assert_eq!(
+            error("plan --baseline ten --lift 0.1", ""),
+            "invalid value 'ten' for --baseline"
+        );
+        assert_eq!(
+            error("analyze --a 1/10 --b 2/10 --format xml", ""),
+            "invalid value 'xml' for --format"
+        );
+        assert!(error("frobnicate", "").starts_with("unknown command 'frobnicate'"));
+    }
+
+    #[test]
+    fn help_is_printed_without_running() {
+        assert_eq!(cli("", "").unwrap(), USAGE);
+        assert_eq!(cli("plan --help --baseline", "").unwrap(), PLAN);
+    }
+}
@@ -0,0 +1,275 @@
+//! Rendering of command results as text or JSON.
+
+use std::fmt::Write;
+
+/// How results are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Sentences followed by aligned `key: value` lines.
+    #[default]
+    Text,
+    /// A single JSON object holding the fields.
+    Json,
+}
+
+/// A field value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Integer(u64),
+    Number(f64),
+    Text(String),
+    List(Vec<Value>),
+    Object(Report),
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Integer(value.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<Report> for Value {
+    fn from(value: Report) -> Self {
+        Value::Object(value)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        Value::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+/// The outcome of a command: sentences for people and fields for both
+/// people and programs.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Report {
+    lines: Vec<String>,
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl Report {
+    /// Adds a sentence, shown in text output only.
+    pub fn line(&mut self, line: impl Into<String>) -> &mut Self {
+        self.lines.push(line.into());
+        self
+    }
+
+    /// Adds a field.
+    pub fn field(&mut self, key: &'static str, value: impl Into<Value>) -> &mut Self {
+        self.fields.push((key, value.into()));
+        self
+    }
+
+    /// Renders the report, ending with a newline.
+    pub fn render(&self, format: Format) -> String {
+        let mut out = String::new();
+        match format {
+            Format::Text => {
+                for line in &self.lines {
+                    out.push_str(line);
+                    out.push('\n');
+                }
+                if !self.lines.is_empty() && !self.fields.is_empty() {
+                    out.push('\n');
+                }
+                self.text_fields(&mut out, 0);
+            }
+            Format::Json => {
+                self.json(&mut out);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    fn text_fields(&self, out: &mut String, indent: usize) {
+        let width = self
+            .fields
+            .iter()
+            .map(|(key, _)| key.len())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        for (key, value) in &self.fields {
+            let label = format!("{}:", key);
+            match value {
+                Value::Object(report) => {
+                    let _ = writeln!(out, "{:indent$}{}", "", label);
+                    report.text_fields(out, indent + 2);
+                }
+                value => {
+                    let _ = write!(out, "{:indent$}{:<width$}  ", "", label);
+                    text_value(out, value);
+                    out.push('\n');
+                }
+            }
+        }
+    }
+
+    fn json(&self, out: &mut String) {
+        out.push('{');
+        for (i, (key, value)) in self.fields.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            json_string(out, key);
+            out.push(':');
+            json_value(out, value);
+        }
+        out.push('}');
+    }
+}
+
+fn text_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push('-'),
+        Value::Bool(b) => out.push_str(if *b { "yes" } else { "no" }),
+        Value::Integer(n) => {
+            let _ = write!(out, "{}", n);
+        }
+        Value::Number(x) => {
+            let _ = if x.is_finite() && x.abs() < 1e-3 && *x != 0.0 {
+                write!(out, "{:.3e}", x)
+            } else {
+                write!(out, "{:.4}", x)
+            };
+        }
+        Value::Text(s) => out.push_str(s),
+        Value::List(values) => {
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                text_value(out, value);
+            }
+        }
+        Value::Object(report) => report.json(out),
+    }
+}
+
+fn json_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => {
+            let _ = write!(out, "{}", b);
+        }
+        Value::Integer(n) => {
+            let _ = write!(out, "{}", n);
+        }
+        // JSON has no infinities or NaN.
+        Value::Number(x) if !x.is_finite() => out.push_str("null"),
+        // Debug formatting switches to exponents for very small and very
+        // large numbers, which JSON accepts.
+        Value::Number(x) => {
+            let _ = write!(out, "{:?}", x);
+        }
+        Value::Text(s) => json_string(out, s),
+        Value::List(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                json_value(out, value);
+            }
+            out.push(']');
+        }
+        Value::Object(report) => report.json(out),
+    }
+}
+
+fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_escapes_strings_and_nulls_non_finite_numbers() {
+        let mut nested = Report::default();
+        nested.field("nan", f64::NAN);
+        let mut report = Report::default();
+        report
+            .line("Not in JSON.")
+            .field("text", "quote \" backslash \\ newline \n tab \t bell \u{7}")
+            .field("infinite", vec![f64::INFINITY, f64::NEG_INFINITY, 0.5])
+            .field("missing", None::<u64>)
+            .field("nested", nested);
+        assert_eq!(
+            report.render(Format::Json),
+            "{\"text\":\"quote \\\" backslash \\\\ newline \\n tab \\t bell \\u0007\",\
+             \"infinite\":[null,null,0.5],\"missing\":null,\"nested\":{\"nan\":null}}\n"
+        );
+    }
+
+    #[test]
+    fn text_aligns_fields_after_the_lines() {
+        let mut nested = Report::default();
+        nested.field("ok", true);
+        let mut report = Report::default();
+        report
+            .line("Done.")
+            .field("p", 0.000_12)
+            .field("count", 3u64)
+            .field("nested", nested);
+        assert_eq!(
+            report.render(Format::Text),
+            "Done.\n\np:       1.200e-4\ncount:   3\nnested:\n  ok:  yes\n"
+        );
+    }
+}
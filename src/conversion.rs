@@ -0,0 +1,526 @@
+//! Two-proportion tests for comparing the conversion rates of two variants.
+
+use crate::distributions::normal;
+use crate::error::{Error, Result};
+use crate::exact;
+use crate::interval::{self, IntervalMethod, OddsRatioMethod, RatioMethod};
+use crate::srm::{self, SrmPolicy, SrmResult};
+
+/// The smallest number of samples per variant [`ab_conversion_test`] accepts.
+pub const MIN_SAMPLE_SIZE: u64 = 5;
+
+/// The observed conversions of one variant.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::Arm;
+///
+/// let arm = Arm::new(200, 1000);
+/// assert_eq!(arm.rate(), 0.2);
+/// assert!(Arm::new(12, 10).validate().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Arm {
+    /// The number of units that converted.
+    pub successes: u64,
+    /// The number of units exposed to the variant.
+    pub trials: u64,
+}
+
+impl Arm {
+    /// Creates an arm from its conversion and exposure counts.
+    pub fn new(successes: u64, trials: u64) -> Self {
+        Arm { successes, trials }
+    }
+
+    /// The conversion rate, `successes / trials`.
+    pub fn rate(&self) -> f64 {
+        self.successes as f64 / self.trials as f64
+    }
+
+    /// Checks that the arm did not convert more units than it exposed.
+    pub fn validate(&self) -> Result<()> {
+        if self.successes > self.trials {
+            return Err(Error::SuccessesExceedTrials {
+                successes: self.successes,
+                trials: self.trials,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// One of the two variants compared in an A/B test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variant {
+    /// The first variant, usually the control.
+    A,
+    /// The second variant, usually the treatment.
+    B,
+}
+
+/// The alternative hypothesis a test is run against.
+///
+/// Directions refer to the difference `p2 - p1`: [`Alternative::Greater`]
+/// is the superiority hypothesis that version B converts better than A.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Alternative {
+    /// The rates differ in either direction.
+    #[default]
+    TwoSided,
+    /// Version B converts better than version A.
+    Greater,
+    /// Version A converts better than version B.
+    Less,
+}
+
+impl Alternative {
+    /// The p-value of a standard normal statistic `z` under this alternative.
+    pub fn p_value(self, z: f64) -> f64 {
+        match self {
+            Alternative::TwoSided => (2.0 * normal::sf(z.abs())).min(1.0),
+            Alternative::Greater => normal::sf(z),
+            Alternative::Less => normal::cdf(z),
+        }
+    }
+
+    /// The magnitude a standard normal statistic must exceed, in the direction
+    /// of the alternative, to be significant at level `alpha`.
+    pub fn critical_value(self, alpha: f64) -> f64 {
+        match self {
+            Alternative::TwoSided => normal::quantile(1.0 - alpha / 2.0),
+            Alternative::Greater | Alternative::Less => normal::quantile(1.0 - alpha),
+        }
+    }
+
+    /// The variant favoured by a significant statistic `z`, if it is
+    /// significant at level `alpha`.
+    pub fn winner(self, z: f64, alpha: f64) -> Option<Variant> {
+        let critical = self.critical_value(alpha);
+        match self {
+            Alternative::TwoSided if z > critical => Some(Variant::B),
+            Alternative::TwoSided if z < -critical => Some(Variant::A),
+            Alternative::Greater if z > critical => Some(Variant::B),
+            Alternative::Less if z < -critical => Some(Variant::A),
+            _ => None,
+        }
+    }
+}
+
+/// Settings shared by the conversion tests.
+///
+/// The defaults reproduce the classic test: a two-sided test at the 5%
+/// significance level with a 95% confidence interval.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::TestOptions;
+///
+/// let options = TestOptions::default().alpha(0.01).confidence(0.99);
+/// assert_eq!(options.alpha, 0.01);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TestOptions {
+    /// The significance level at which the null hypothesis is rejected.
+    pub alpha: f64,
+    /// The coverage of the reported confidence interval.
+    pub confidence: f64,
+    /// The alternative hypothesis. One-sided alternatives report the matching
+    /// one-sided confidence bound.
+    pub alternative: Alternative,
+    /// How the confidence interval for the difference is built.
+    pub interval: IntervalMethod,
+    /// How the confidence interval for the relative lift is built.
+    pub ratio_interval: RatioMethod,
+    /// How the confidence interval for the odds ratio is built.
+    pub odds_ratio_interval: OddsRatioMethod,
+    /// Whether Fisher's exact test, and the exact odds ratio interval built
+    /// on it, report mid-p values, which count the observed table for half.
+    /// Barnard's and Boschloo's tests and the normal approximation ignore
+    /// this setting.
+    pub mid_p: bool,
+    /// What the z-tests do about a sample ratio mismatch.
+    pub srm: SrmPolicy,
+    /// The p-value below which the traffic split counts as a mismatch.
+    pub srm_alpha: f64,
+    /// The intended number of treatment units per control unit, against
+    /// which the traffic split is checked.
+    pub allocation: f64,
+}
+
+impl Default for TestOptions {
+    fn default() -> Self {
+        TestOptions {
+            alpha: 0.05,
+            confidence: 0.95,
+            alternative: Alternative::TwoSided,
+            interval: IntervalMethod::Wald,
+            ratio_interval: RatioMethod::Log,
+            odds_ratio_interval: OddsRatioMethod::Woolf,
+            mid_p: false,
+            srm: SrmPolicy::Flag,
+            srm_alpha: 0.001,
+            allocation: 1.0,
+        }
+    }
+}
+
+impl TestOptions {
+    /// Sets the significance level.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the confidence level of the reported interval.
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    /// Sets the alternative hypothesis.
+    pub fn alternative(mut self, alternative: Alternative) -> Self {
+        self.alternative = alternative;
+        self
+    }
+
+    /// Sets the confidence interval method.
+    pub fn interval(mut self, interval: IntervalMethod) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the confidence interval method for the relative lift.
+    pub fn ratio_interval(mut self, ratio_interval: RatioMethod) -> Self {
+        self.ratio_interval = ratio_interval;
+        self
+    }
+
+    /// Sets the confidence interval method for the odds ratio.
+    pub fn odds_ratio_interval(mut self, odds_ratio_interval: OddsRatioMethod) -> Self {
+        self.odds_ratio_interval = odds_ratio_interval;
+        self
+    }
+
+    /// Sets whether Fisher's exact test reports mid-p values.
+    pub fn mid_p(mut self, mid_p: bool) -> Self {
+        self.mid_p = mid_p;
+        self
+    }
+
+    /// Sets what to do about a sample ratio mismatch.
+    pub fn srm(mut self, srm: SrmPolicy) -> Self {
+        self.srm = srm;
+        self
+    }
+
+    /// Sets the p-value threshold of the sample ratio mismatch check.
+    pub fn srm_alpha(mut self, srm_alpha: f64) -> Self {
+        self.srm_alpha = srm_alpha;
+        self
+    }
+
+    /// Sets the intended number of treatment units per control unit.
+    pub fn allocation(mut self, allocation: f64) -> Self {
+        self.allocation = allocation;
+        self
+    }
+
+    /// Checks that the levels lie strictly between 0 and 1 and that the
+    /// allocation is positive.
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("alpha", self.alpha),
+            ("confidence", self.confidence),
+            ("srm_alpha", self.srm_alpha),
+        ] {
+            if !(value > 0.0 && value < 1.0) {
+                return Err(Error::InvalidParameter { name, value });
+            }
+        }
+        if !(self.allocation > 0.0 && self.allocation.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "allocation",
+                value: self.allocation,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of [`ab_conversion_test`].
+///
+/// Differences are always expressed as `p2 - p1`, so a positive value means
+/// version B converts better than version A. The test statistic uses the
+/// pooled variance that holds under the null hypothesis, while the confidence
+/// interval is an estimate of the difference and so uses the unpooled
+/// variance, or one of the alternatives in [`IntervalMethod`]. The relative
+/// lift and the odds ratio come with intervals built as set by
+/// [`TestOptions::ratio_interval`] and [`TestOptions::odds_ratio_interval`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConversionTestResult {
+    /// The variant with the higher conversion rate, or `None` when the
+    /// difference is not statistically significant.
+    pub winner: Option<Variant>,
+    /// The z statistic of the pooled two-proportion test, signed like `difference`.
+    pub z: f64,
+    /// The p-value under the alternative in the test options; two-sided by
+    /// default.
+    pub p_value: f64,
+    /// The one-sided p-value for the alternative that B converts better than A.
+    pub p_value_greater: f64,
+    /// The one-sided p-value for the alternative that A converts better than B.
+    pub p_value_less: f64,
+    /// The signed difference of conversion rates, `p2 - p1`.
+    pub difference: f64,
+    /// Lower bound of the confidence interval for `difference`; `-1` for a
+    /// [`Alternative::Less`] test.
+    pub ci_low: f64,
+    /// Upper bound of the confidence interval for `difference`; `1` for a
+    /// [`Alternative::Greater`] test.
+    pub ci_high: f64,
+    /// The conversion rate of both variants taken together.
+    pub pooled_rate: f64,
+    /// The relative lift `p2 / p1 - 1`, so `0.1` means B converts 10%
+    /// better than A; infinite if A never converted.
+    pub relative_lift: f64,
+    /// Lower bound of the confidence interval for `relative_lift`; `-1`
+    /// for a [`Alternative::Less`] test.
+    pub relative_ci_low: f64,
+    /// Upper bound of the confidence interval for `relative_lift`;
+    /// infinite for a [`Alternative::Greater`] test.
+    pub relative_ci_high: f64,
+    /// The odds ratio of B against A.
+    pub odds_ratio: f64,
+    /// Lower bound of the confidence interval for `odds_ratio`; `0` for a
+    /// [`Alternative::Less`] test.
+    pub odds_ratio_ci_low: f64,
+    /// Upper bound of the confidence interval for `odds_ratio`; infinite
+    /// for a [`Alternative::Greater`] test.
+    pub odds_ratio_ci_high: f64,
+    /// The sample ratio mismatch check of the arm sizes, unless the options
+    /// turn it off. A flagged mismatch casts doubt on `winner`.
+    pub srm: Option<SrmResult>,
+}
+
+/// Conducts an A/B test on two given proportions and outputs the winner if any.
+///
+/// The function performs a hypothesis test of the null hypothesis that the two
+/// proportions are equal. If the null could be rejected at the 5% significance
+/// level, the result names the version with the higher conversion rate as the
+/// winner. The full statistics, including the 95% confidence interval for the
+/// difference of proportions, are reported whether or not the test is
+/// significant. Use [`ab_conversion_test_with`] for other levels.
+///
+/// # Arguments
+///
+/// * `p1` - The conversion rate of version A.
+/// * `n1` - The number of samples exposed to version A.
+/// * `p2` - The conversion rate of version B.
+/// * `n2` - The number of samples exposed to version B.
+///
+/// # Errors
+///
+/// Returns an [`Error`] if a proportion is outside `[0, 1]`, if either sample
+/// has fewer than [`MIN_SAMPLE_SIZE`] observations, if both rates are 0 or
+/// both are 1, or if `n1 + n2` overflows.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::{ab_conversion_test, Variant};
+///
+/// let n1 = 1000;
+/// let x1 = 200;
+/// let n2 = 800;
+/// let x2 = 560;
+/// let p1 = x1 as f64 / n1 as f64;
+/// let p2 = x2 as f64 / n2 as f64;
+///
+/// let result = ab_conversion_test(p1, n1, p2, n2).unwrap();
+/// assert_eq!(result.winner, Some(Variant::B));
+/// assert!(result.ci_low > 0.0);
+/// assert!(result.p_value < 1e-50);
+/// // B converts 250% better, and at least 200% better.
+/// assert!((result.relative_lift - 2.5).abs() < 1e-12);
+/// assert!(result.relative_ci_low > 2.0);
+/// ```
+pub fn ab_conversion_test(p1: f64, n1: usize, p2: f64, n2: usize) -> Result<ConversionTestResult> {
+    ab_conversion_test_with(p1, n1, p2, n2, &TestOptions::default())
+}
+
+/// Conducts an A/B test on two given proportions with the settings in `options`.
+///
+/// For a two-sided test the critical value is the `1 - alpha / 2` quantile of
+/// the standard normal distribution and the confidence interval uses the
+/// `(1 + confidence) / 2` quantile. One-sided tests use the `1 - alpha` and
+/// `confidence` quantiles, and only name the winner the alternative points
+/// to. The interval is built with the method in `options.interval`.
+///
+/// # Errors
+///
+/// Returns the same errors as [`ab_conversion_test`],
+/// [`Error::InvalidParameter`] for invalid options, and
+/// [`Error::SampleRatioMismatch`] if the options block results whose arm
+/// sizes fail the sample ratio mismatch check.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::{ab_conversion_test_with, Alternative, TestOptions};
+/// use statistical_computing::{ab_conversion_test, Variant};
+///
+/// // Not significant at the default 5% level...
+/// assert!(ab_conversion_test(0.10, 1000, 0.125, 1000).unwrap().winner.is_none());
+///
+/// // ...but significant at 10%...
+/// let options = TestOptions::default().alpha(0.10).confidence(0.90);
+/// let result = ab_conversion_test_with(0.10, 1000, 0.125, 1000, &options).unwrap();
+/// assert_eq!(result.winner, Some(Variant::B));
+///
+/// // ...and in a one-sided test that B beats A.
+/// let options = TestOptions::default().alternative(Alternative::Greater);
+/// let result = ab_conversion_test_with(0.10, 1000, 0.125, 1000, &options).unwrap();
+/// assert_eq!(result.winner, Some(Variant::B));
+/// assert_eq!(result.p_value, result.p_value_greater);
+/// ```
+pub fn ab_conversion_test_with(
+    p1: f64,
+    n1: usize,
+    p2: f64,
+    n2: usize,
+    options: &TestOptions,
+) -> Result<ConversionTestResult> {
+    options.validate()?;
+    for p in [p1, p2] {
+        if !(0.0..=1.0).contains(&p) {
+            return Err(Error::InvalidProportion(p));
+        }
+    }
+    check_sample_sizes(n1 as u64, n2 as u64)?;
+    two_proportion_test(p1, n1 as f64, p2, n2 as f64, options)
+}
+
+/// Conducts an A/B test on the conversion counts of two variants.
+///
+/// This is the same test as [`ab_conversion_test_with`], but it takes the
+/// integer counts directly rather than rates the caller has divided out.
+///
+/// # Errors
+///
+/// Returns [`Error::SuccessesExceedTrials`] if an arm converted more units
+/// than it exposed, and otherwise the same errors as
+/// [`ab_conversion_test_with`].
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::{ab_conversion_test_counts, Arm, TestOptions};
+/// use statistical_computing::Variant;
+///
+/// let a = Arm::new(200, 1000);
+/// let b = Arm::new(560, 800);
+/// let result = ab_conversion_test_counts(a, b, &TestOptions::default()).unwrap();
+/// assert_eq!(result.winner, Some(Variant::B));
+/// // A 1000/800 split is implausible for an even allocation.
+/// assert!(result.srm.unwrap().mismatch);
+/// ```
+pub fn ab_conversion_test_counts(
+    a: Arm,
+    b: Arm,
+    options: &TestOptions,
+) -> Result<ConversionTestResult> {
+    options.validate()?;
+    a.validate()?;
+    b.validate()?;
+    check_sample_sizes(a.trials, b.trials)?;
+    two_proportion_test(
+        a.rate(),
+        a.trials as f64,
+        b.rate(),
+        b.trials as f64,
+        options,
+    )
+}
+
+/// Rejects samples too small for the normal approximation, or too large to
+/// be combined.
+fn check_sample_sizes(n1: u64, n2: u64) -> Result<()> {
+    let smallest = n1.min(n2);
+    if smallest < MIN_SAMPLE_SIZE {
+        return Err(Error::InsufficientSample {
+            required: MIN_SAMPLE_SIZE,
+            actual: smallest,
+        });
+    }
+    n1.checked_add(n2).ok_or(Error::Overflow)?;
+    Ok(())
+}
+
+/// The pooled two-proportion z-test on validated inputs.
+fn two_proportion_test(
+    p1: f64,
+    n1: f64,
+    p2: f64,
+    n2: f64,
+    options: &TestOptions,
+) -> Result<ConversionTestResult> {
+    let srm = srm::check(&[n1 as u64, n2 as u64], options)?;
+    let p = (p1 * n1 + p2 * n2) / (n1 + n2);
+    if p <= 0.0 || p >= 1.0 {
+        return Err(Error::ZeroVariance);
+    }
+    let difference = p2 - p1;
+    let denominator = (p * (1.0 - p) * ((1.0 / n1) + (1.0 / n2))).sqrt();
+    let z = difference / denominator;
+
+    let level = match options.alternative {
+        Alternative::TwoSided => (1.0 + options.confidence) / 2.0,
+        _ => options.confidence,
+    };
+    let critical = normal::quantile(level);
+    let one_sided = |(lo, hi): (f64, f64), min: f64, max: f64| match options.alternative {
+        Alternative::TwoSided => (lo, hi),
+        Alternative::Greater => (lo, max),
+        Alternative::Less => (min, hi),
+    };
+    let (lo, hi) = one_sided(
+        interval::difference(options.interval, p1, n1, p2, n2, critical),
+        -1.0,
+        1.0,
+    );
+    let (ratio_low, ratio_high) = one_sided(
+        interval::ratio(options.ratio_interval, p1, n1, p2, n2, critical),
+        0.0,
+        f64::INFINITY,
+    );
+    let (odds_ratio, woolf_low, woolf_high) = interval::woolf(p1, n1, p2, n2, critical);
+    let (odds_ratio_ci_low, odds_ratio_ci_high) = match options.odds_ratio_interval {
+        OddsRatioMethod::Woolf => one_sided((woolf_low, woolf_high), 0.0, f64::INFINITY),
+        OddsRatioMethod::Exact => {
+            let arm = |p: f64, n: f64| Arm::new((p * n).round() as u64, n as u64);
+            exact::odds_ratio_interval(arm(p1, n1), arm(p2, n2), options)
+        }
+    };
+
+    Ok(ConversionTestResult {
+        winner: options.alternative.winner(z, options.alpha),
+        z,
+        p_value: options.alternative.p_value(z),
+        p_value_greater: Alternative::Greater.p_value(z),
+        p_value_less: Alternative::Less.p_value(z),
+        difference,
+        ci_low: lo,
+        ci_high: hi,
+        pooled_rate: p,
+        relative_lift: p2 / p1 - 1.0,
+        relative_ci_low: ratio_low - 1.0,
+        relative_ci_high: ratio_high - 1.0,
+        odds_ratio,
+        odds_ratio_ci_low,
+        odds_ratio_ci_high,
+        srm,
+    })
+}
@@ -0,0 +1,221 @@
+//! Corrections for running many tests at once.
+//!
+//! Each test in this crate controls its own error rate, so comparing several
+//! arms or several metrics at the same level inflates the chance of a false
+//! winner. The adjustments here take the raw p-values of a family of tests
+//! and return p-values that can be compared against the original `alpha`.
+
+use crate::distributions::normal;
+use crate::error::{Error, Result};
+
+/// A multiple-comparison procedure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Correction {
+    /// Bonferroni's correction, controlling the family-wise error rate.
+    Bonferroni,
+    /// Šidák's correction, exact for independent tests.
+    Sidak,
+    /// Holm's step-down procedure, uniformly more powerful than Bonferroni.
+    Holm,
+    /// Hochberg's step-up procedure, valid for independent or positively
+    /// dependent tests.
+    Hochberg,
+    /// The Benjamini-Hochberg procedure, controlling the false discovery rate
+    /// under independence or positive dependence.
+    BenjaminiHochberg,
+    /// The Benjamini-Yekutieli procedure, controlling the false discovery rate
+    /// under arbitrary dependence.
+    BenjaminiYekutieli,
+}
+
+/// Adjusted p-values and the decisions they lead to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdjustedPValues {
+    /// The adjusted p-values, in the order the raw p-values were given.
+    pub adjusted: Vec<f64>,
+    /// Whether each hypothesis is rejected at the family-wise level.
+    pub rejected: Vec<bool>,
+}
+
+impl AdjustedPValues {
+    fn new(adjusted: Vec<f64>, alpha: f64) -> Self {
+        let rejected = adjusted.iter().map(|&p| p < alpha).collect();
+        AdjustedPValues { adjusted, rejected }
+    }
+}
+
+/// Adjusts a family of p-values for multiple comparisons.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidParameter`] if a p-value is outside `[0, 1]` or
+/// `alpha` is not strictly between 0 and 1.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::correction::{adjust, Correction};
+///
+/// let p_values = [0.01, 0.04, 0.03, 0.005];
+/// let holm = adjust(&p_values, Correction::Holm, 0.05).unwrap();
+/// assert_eq!(holm.adjusted, vec![0.03, 0.06, 0.06, 0.02]);
+/// assert_eq!(holm.rejected, vec![true, false, false, true]);
+/// ```
+pub fn adjust(p_values: &[f64], method: Correction, alpha: f64) -> Result<AdjustedPValues> {
+    validate(p_values, alpha)?;
+    let m = p_values.len() as f64;
+    let adjusted = match method {
+        Correction::Bonferroni => p_values.iter().map(|p| (p * m).min(1.0)).collect(),
+        Correction::Sidak => p_values
+            .iter()
+            .map(|p| -(m * (-p).ln_1p()).exp_m1())
+            .collect(),
+        Correction::Holm => step(p_values, false, |rank| m - rank as f64),
+        Correction::Hochberg => step(p_values, true, |rank| m - rank as f64),
+        Correction::BenjaminiHochberg => step(p_values, true, |rank| m / (rank + 1) as f64),
+        Correction::BenjaminiYekutieli => {
+            let harmonic: f64 = (1..=p_values.len()).map(|i| 1.0 / i as f64).sum();
+            step(p_values, true, |rank| harmonic * m / (rank + 1) as f64)
+        }
+    };
+    Ok(AdjustedPValues::new(adjusted, alpha))
+}
+
+/// Dunnett's single-step adjustment for comparing several treatments with a
+/// shared control.
+///
+/// The two-sided p-values of the z-tests against the control are correlated
+/// because every test reuses the control arm. Accounting for that
+/// correlation, which follows from the arm sizes, makes Dunnett's adjustment
+/// less conservative than Bonferroni's while still controlling the
+/// family-wise error rate.
+///
+/// # Arguments
+///
+/// * `p_values` - The two-sided p-values of each treatment against control.
+/// * `control_trials` - The number of units in the control arm.
+/// * `treatment_trials` - The number of units in each treatment arm, in the
+///   same order as `p_values`.
+/// * `alpha` - The family-wise significance level.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidParameter`] for invalid p-values or `alpha`, or if
+/// the lengths of `p_values` and `treatment_trials` differ, and
+/// [`Error::InsufficientSample`] if an arm is empty.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::correction::{adjust, dunnett, Correction};
+///
+/// let p_values = [0.02, 0.01, 0.3];
+/// let dunnett = dunnett(&p_values, 1000, &[1000, 1000, 1000], 0.05).unwrap();
+/// let bonferroni = adjust(&p_values, Correction::Bonferroni, 0.05).unwrap();
+/// assert!(dunnett.adjusted[1] < bonferroni.adjusted[1]);
+/// ```
+pub fn dunnett(
+    p_values: &[f64],
+    control_trials: u64,
+    treatment_trials: &[u64],
+    alpha: f64,
+) -> Result<AdjustedPValues> {
+    validate(p_values, alpha)?;
+    if treatment_trials.len() != p_values.len() {
+        return Err(Error::InvalidParameter {
+            name: "treatment_trials",
+            value: treatment_trials.len() as f64,
+        });
+    }
+    let smallest = treatment_trials
+        .iter()
+        .fold(control_trials, |a, &b| a.min(b));
+    if smallest == 0 {
+        return Err(Error::InsufficientSample {
+            required: 1,
+            actual: 0,
+        });
+    }
+    // Each statistic is lambda * X + sqrt(1 - lambda^2) * E for a shared
+    // standard normal X, giving correlations lambda_i * lambda_j.
+    let lambdas: Vec<f64> = treatment_trials
+        .iter()
+        .map(|&n| (n as f64 / (n as f64 + control_trials as f64)).sqrt())
+        .collect();
+    let adjusted = p_values
+        .iter()
+        .map(|&p| {
+            let critical = normal::quantile(1.0 - p / 2.0);
+            (1.0 - all_within(&lambdas, critical)).clamp(0.0, 1.0)
+        })
+        .collect();
+    Ok(AdjustedPValues::new(adjusted, alpha))
+}
+
+/// `P(|Z_i| < c for all i)` for the one-factor correlated normals described
+/// by `lambdas`, integrating over the shared factor with Simpson's rule.
+fn all_within(lambdas: &[f64], c: f64) -> f64 {
+    if c.is_infinite() {
+        return 1.0;
+    }
+    const STEPS: usize = 2000;
+    const RANGE: f64 = 8.0;
+    let h = 2.0 * RANGE / STEPS as f64;
+    let integrand = |x: f64| {
+        let product: f64 = lambdas
+            .iter()
+            .map(|&l| {
+                let s = (1.0 - l * l).sqrt();
+                normal::cdf((c - l * x) / s) - normal::cdf((-c - l * x) / s)
+            })
+            .product();
+        normal::pdf(x) * product
+    };
+    let mut sum = integrand(-RANGE) + integrand(RANGE);
+    for i in 1..STEPS {
+        let weight = if i % 2 == 1 { 4.0 } else { 2.0 };
+        sum += weight * integrand(-RANGE + i as f64 * h);
+    }
+    sum * h / 3.0
+}
+
+/// Applies a step-down (`up = false`) or step-up (`up = true`) procedure
+/// that multiplies the p-value of rank `r` (0 for the smallest) by
+/// `factor(r)` and enforces monotonicity.
+fn step(p_values: &[f64], up: bool, factor: impl Fn(usize) -> f64) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..p_values.len()).collect();
+    order.sort_by(|&i, &j| p_values[i].total_cmp(&p_values[j]));
+    let mut adjusted = vec![0.0; p_values.len()];
+    if up {
+        let mut running = 1.0f64;
+        for (rank, &i) in order.iter().enumerate().rev() {
+            running = running.min(p_values[i] * factor(rank));
+            adjusted[i] = running.min(1.0);
+        }
+    } else {
+        let mut running = 0.0f64;
+        for (rank, &i) in order.iter().enumerate() {
+            running = running.max(p_values[i] * factor(rank));
+            adjusted[i] = running.min(1.0);
+        }
+    }
+    adjusted
+}
+
+fn validate(p_values: &[f64], alpha: f64) -> Result<()> {
+    if !(alpha > 0.0 && alpha < 1.0) {
+        return Err(Error::InvalidParameter {
+            name: "alpha",
+            value: alpha,
+        });
+    }
+    for &p in p_values {
+        if !(0.0..=1.0).contains(&p) {
+            return Err(Error::InvalidParameter {
+                name: "p_value",
+                value: p,
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,613 @@
+//! Reading experiment event logs from CSV.
+//!
+//! A typical export has one row per event, `user_id,variant,converted,revenue`,
+//! with several rows for users who came back. [`read_events`] streams such a
+//! file and reduces it to one record per user, who converted if any of their
+//! rows converted and whose revenue is the sum over their rows. Each variant
+//! then becomes an [`Arm`] for the conversion tests and a [`Summary`] of
+//! revenue per user for the two-sample tests.
+//!
+//! Quoted fields follow RFC 4180, except that a quoted field cannot span
+//! lines.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+
+use crate::conversion::Arm;
+use crate::error::{Error, Result};
+use crate::two_sample::Summary;
+
+/// A column of the file, by header name or by zero-based position.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Column {
+    /// The column whose header is this name.
+    Name(String),
+    /// The column at this position, counting from zero.
+    Index(usize),
+}
+
+impl From<&str> for Column {
+    fn from(name: &str) -> Self {
+        Column::Name(name.to_string())
+    }
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Self {
+        Column::Index(index)
+    }
+}
+
+/// Settings for [`read_events`].
+///
+/// The defaults read a comma-separated file with a header naming the
+/// columns `user_id`, `variant`, `converted` and `revenue`.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::csv::CsvOptions;
+///
+/// // A headerless, semicolon-separated file without revenue.
+/// let options = CsvOptions::default()
+///     .delimiter(';')
+///     .header(false)
+///     .user(0)
+///     .variant(1)
+///     .converted(2)
+///     .revenue(None);
+/// assert!(options.revenue.is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// The character between fields.
+    pub delimiter: char,
+    /// Whether the first line names the columns rather than holding data.
+    pub header: bool,
+    /// The column identifying the user.
+    pub user: Column,
+    /// The column naming the variant the user saw.
+    pub variant: Column,
+    /// The column saying whether the row converted, as `1`/`0`,
+    /// `true`/`false` or `yes`/`no`.
+    pub converted: Column,
+    /// The column holding the revenue of the row, if any. Empty values
+    /// count as zero.
+    pub revenue: Option<Column>,
+    /// Whether a malformed row is an error rather than being skipped and
+    /// reported.
+    pub strict: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            header: true,
+            user: "user_id".into(),
+            variant: "variant".into(),
+            converted: "converted".into(),
+            revenue: Some("revenue".into()),
+            strict: false,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Sets the character between fields.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets whether the first line is a header.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Sets the user column.
+    pub fn user(mut self, column: impl Into<Column>) -> Self {
+        self.user = column.into();
+        self
+    }
+
+    /// Sets the variant column.
+    pub fn variant(mut self, column: impl Into<Column>) -> Self {
+        self.variant = column.into();
+        self
+    }
+
+    /// Sets the conversion column.
+    pub fn converted(mut self, column: impl Into<Column>) -> Self {
+        self.converted = column.into();
+        self
+    }
+
+    /// Sets the revenue column, or `None` if there is none.
+    pub fn revenue(mut self, column: Option<Column>) -> Self {
+        self.revenue = column;
+        self
+    }
+
+    /// Sets whether a malformed row is an error.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+/// A row that could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MalformedRow {
+    /// The line of the row, counting from one.
+    pub line: u64,
+    /// What was wrong with it.
+    pub reason: String,
+}
+
+/// The users of one variant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantData {
+    /// The variant as written in the file.
+    pub name: String,
+    /// The users who converted, out of all users of the variant.
+    pub arm: Arm,
+    /// The revenue per user, if the file has a revenue column.
+    pub revenue: Option<Summary>,
+}
+
+/// The outcome of [`read_events`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Events {
+    /// Each variant, sorted by name.
+    pub variants: Vec<VariantData>,
+    /// The rows read, excluding the header and malformed rows.
+    pub rows: u64,
+    /// The rows that repeated an earlier user and were merged into them.
+    pub duplicate_rows: u64,
+    /// The users seen in more than one variant, who are left out of every
+    /// variant since their outcome cannot be attributed to either.
+    pub crossover_users: u64,
+    /// The rows that were skipped, in file order.
+    pub malformed: Vec<MalformedRow>,
+}
+
+impl Events {
+    /// The variant with the given name.
+    pub fn variant(&self, name: &str) -> Option<&VariantData> {
+        self.variants.iter().find(|v| v.name == name)
+    }
+
+    /// The arm of every variant, in the order of [`Events::variants`].
+    pub fn arms(&self) -> Vec<Arm> {
+        self.variants.iter().map(|v| v.arm).collect()
+    }
+}
+
+/// One user's rows, merged.
+struct User {
+    variant: usize,
+    converted: bool,
+    revenue: f64,
+    crossover: bool,
+}
+
+/// Reads an event log and aggregates it per user and then per variant.
+///
+/// The reader is consumed line by line, so only one record per user is held
+/// in memory. Blank lines are ignored.
+///
+/// # Errors
+///
+/// Returns [`Error::Csv`] if the file cannot be read, if the header lacks a
+/// named column or a column is named without a header, and, with
+/// `options.strict`, for the first malformed row.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::{ab_conversion_test_counts, TestOptions};
+/// use statistical_computing::csv::{read_events, CsvOptions};
+/// use statistical_computing::two_sample::welch_t_test;
+///
+/// let data = "\
+/// user_id,variant,converted,revenue
+/// u1,control,0,
+/// u2,control,1,20.0
+/// u2,control,1,5.0
+/// u3,control,0,
+/// u4,control,0,
+/// u5,control,1,\"12.5\"
+/// u6,treatment,1,30.0
+/// u7,treatment,yes,18.0
+/// u8,treatment,0,
+/// u9,treatment,maybe,
+/// u9,treatment,1,25.0
+/// u10,treatment,1,22.0
+/// u11,treatment,true,9.0
+/// ";
+/// let events = read_events(data.as_bytes(), &CsvOptions::default()).unwrap();
+/// assert_eq!(events.duplicate_rows, 1);
+/// assert_eq!(events.malformed[0].line, 11);
+///
+/// let (a, b) = (&events.variants[0], &events.variants[1]);
+/// assert_eq!((a.arm.successes, a.arm.trials), (2, 5));
+/// assert!((a.revenue.unwrap().mean - 7.5).abs() < 1e-12);
+/// let options = TestOptions::default();
+/// let conversion = ab_conversion_test_counts(a.arm, b.arm, &options).unwrap();
+/// let revenue = welch_t_test(a.revenue.unwrap(), b.revenue.unwrap(), &options.into()).unwrap();
+/// assert!(conversion.difference > 0.0 && revenue.difference > 0.0);
+/// ```
+pub fn read_events<R: BufRead>(reader: R, options: &CsvOptions) -> Result<Events> {
+    let mut events = Events::default();
+    let mut columns: Option<Columns> = None;
+    let mut names: Vec<String> = Vec::new();
+    let mut name_index: HashMap<String, usize> = HashMap::new();
+    let mut users: Vec<User> = Vec::new();
+    let mut user_index: HashMap<String, usize> = HashMap::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let number = i as u64 + 1;
+        let line = line.map_err(|e| Error::Csv {
+            line: number,
+            message: e.to_string(),
+        })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split(&line, options.delimiter);
+        if columns.is_none() {
+            if options.header {
+                let header = fields.map_err(|reason| Error::Csv {
+                    line: number,
+                    message: reason,
+                })?;
+                columns = Some(Columns::resolve(Some(&header), options, number)?);
+                continue;
+            }
+            columns = Some(Columns::resolve(None, options, number)?);
+        }
+        let columns = columns
+            .as_ref()
+            .expect("columns are resolved on the first line");
+
+        let record = match &fields {
+            Ok(fields) => columns.record(fields),
+            Err(reason) => Err(reason.clone()),
+        };
+        let (user, variant, converted, revenue) = match record {
+            Ok(record) => record,
+            Err(reason) => {
+                if options.strict {
+                    return Err(Error::Csv {
+                        line: number,
+                        message: reason,
+                    });
+                }
+                events.malformed.push(MalformedRow {
+                    line: number,
+                    reason,
+                });
+                continue;
+            }
+        };
+        events.rows += 1;
+
+        let variant = match name_index.get(variant) {
+            Some(&index) => index,
+            None => {
+                name_index.insert(variant.to_string(), names.len());
+                names.push(variant.to_string());
+                names.len() - 1
+            }
+        };
+        match user_index.get(user) {
+            Some(&index) => {
+                events.duplicate_rows += 1;
+                let record = &mut users[index];
+                record.converted |= converted;
+                record.revenue += revenue;
+                record.crossover |= record.variant != variant;
+            }
+            None => {
+                user_index.insert(user.to_string(), users.len());
+                users.push(User {
+                    variant,
+                    converted,
+                    revenue,
+                    crossover: false,
+                });
+            }
+        }
+    }
+
+    // Per-variant counts and Welford's running moments of revenue.
+    let mut totals = vec![(0u64, 0u64, 0.0, 0.0); names.len()];
+    for user in &users {
+        if user.crossover {
+            events.crossover_users += 1;
+            continue;
+        }
+        let (trials, successes, mean, squares) = &mut totals[user.variant];
+        *trials += 1;
+        *successes += u64::from(user.converted);
+        let delta = user.revenue - *mean;
+        *mean += delta / *trials as f64;
+        *squares += delta * (user.revenue - *mean);
+    }
+    events.variants = names
+        .into_iter()
+        .zip(totals)
+        .map(|(name, (trials, successes, mean, squares))| VariantData {
+            name,
+            arm: Arm::new(successes, trials),
+            revenue: options.revenue.as_ref().map(|_| {
+                let variance = if trials > 1 {
+                    squares / (trials - 1) as f64
+                } else {
+                    0.0
+                };
+                Summary::new(trials, mean, variance)
+            }),
+        })
+        .collect();
+    events.variants.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(events)
+}
+
+/// The positions of the columns that are read.
+struct Columns {
+    user: usize,
+    variant: usize,
+    converted: usize,
+    revenue: Option<usize>,
+}
+
+impl Columns {
+    fn resolve(header: Option<&[String]>, options: &CsvOptions, line: u64) -> Result<Self> {
+        let position = |column: &Column| match (column, header) {
+            (Column::Index(index), _) => Ok(*index),
+            (Column::Name(name), Some(header)) => header
+                .iter()
+                .position(|h| h.trim_start_matches('\u{feff}').trim() == name)
+                .ok_or_else(|| Error::Csv {
+                    line,
+                    message: format!("the header has no column '{}'", name),
+                }),
+            (Column::Name(name), None) => Err(Error::Csv {
+                line,
+                message: format!("column '{}' is named, but the file has no header", name),
+            }),
+        };
+        Ok(Columns {
+            user: position(&options.user)?,
+            variant: position(&options.variant)?,
+            converted: position(&options.converted)?,
+            revenue: options.revenue.as_ref().map(position).transpose()?,
+        })
+    }
+
+    /// The user, variant, conversion and revenue of a row.
+    fn record<'a>(&self, fields: &'a [String]) -> std::result::Result<Record<'a>, String> {
+        let needed = [self.user, self.variant, self.converted]
+            .into_iter()
+            .chain(self.revenue)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        if fields.len() < needed {
+            return Err(format!(
+                "expected at least {} fields, found {}",
+                needed,
+                fields.len()
+            ));
+        }
+        let user = fields[self.user].trim();
+        if user.is_empty() {
+            return Err("the user id is empty".to_string());
+        }
+        let variant = fields[self.variant].trim();
+        if variant.is_empty() {
+            return Err("the variant is empty".to_string());
+        }
+        let converted = match fields[self.converted].trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" => true,
+            "0" | "false" | "no" => false,
+            other => return Err(format!("'{}' is not a conversion flag", other)),
+        };
+        let revenue = match self.revenue.map(|i| fields[i].trim()) {
+            None | Some("") => 0.0,
+            Some(value) => match value.parse::<f64>() {
+                Ok(revenue) if revenue.is_finite() => revenue,
+                _ => return Err(format!("'{}' is not a revenue amount", value)),
+            },
+        };
+        Ok((user, variant, converted, revenue))
+    }
+}
+
+type Record<'a> = (&'a str, &'a str, bool, f64);
+
+/// Splits a line into fields, removing quotes and unescaping doubled ones.
+fn split(line: &str, delimiter: char) -> std::result::Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                }
+            } else {
+                field.push(c);
+            }
+        } else if c == '"' && field.trim().is_empty() {
+            field.clear();
+            quoted = true;
+        } else if c == delimiter {
+            fields.push(std::mem::take(&mut field));
+        } else {
+            field.push(c);
+        }
+    }
+    if quoted {
+        return Err("a quoted field is not closed".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "\
+user_id,variant,converted,revenue
+u1,control,0,
+u2,control,1,20.0
+u2,control,0,5.0
+u3,control,maybe,
+u4,treatment,1,30.0
+
+u5,treatment,no,
+u6,control,0,
+u6,treatment,1,8.0
+u7,treatment,true,\"1,5\"
+";
+
+    #[test]
+    fn users_are_merged_and_crossovers_left_out() {
+        let events = read_events(LOG.as_bytes(), &CsvOptions::default()).unwrap();
+        assert_eq!(events.rows, 7);
+        assert_eq!(events.duplicate_rows, 2);
+        assert_eq!(events.crossover_users, 1);
+
+        let control = events.variant("control").unwrap();
+        assert_eq!(control.arm, Arm::new(1, 2));
+        let revenue = control.revenue.unwrap();
+        assert_eq!((revenue.n, revenue.mean), (2, 12.5));
+        let treatment = events.variant("treatment").unwrap();
+        assert_eq!(treatment.arm, Arm::new(1, 2));
+        assert_eq!(treatment.revenue.unwrap().mean, 15.0);
+        assert_eq!(events.arms(), vec![control.arm, treatment.arm]);
+
+        // Line numbers count the header and the blank line.
+        assert_eq!(
+            events.malformed,
+            vec![
+                MalformedRow {
+                    line: 5,
+                    reason: "'maybe' is not a conversion flag".to_string(),
+                },
+                MalformedRow {
+                    line: 11,
+                    reason: "'1,5' is not a revenue amount".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn crlf_line_endings_read_the_same() {
+        let crlf = LOG.replace('\n', "\r\n");
+        let events = read_events(crlf.as_bytes(), &CsvOptions::default()).unwrap();
+        assert_eq!(
+            events,
+            read_events(LOG.as_bytes(), &CsvOptions::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn strict_reading_stops_at_the_first_malformed_row() {
+        let options = CsvOptions::default().strict(true);
+        assert_eq!(
+            read_events(LOG.as_bytes(), &options),
+            Err(Error::Csv {
+                line: 5,
+                message: "'maybe' is not a conversion flag".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn split_handles_quotes() {
+        assert_eq!(
+            split("a,,c", ','),
+            Ok(vec!["a".into(), "".into(), "c".into()])
+        );
+        assert_eq!(
+            split("u1,\"a,b\", \"say \"\"hi\"\"\",1", ','),
+            Ok(vec![
+                "u1".into(),
+                "a,b".into(),
+                "say \"hi\"".into(),
+                "1".into()
+            ])
+        );
+        // A quote inside an unquoted field is kept as written.
+        assert_eq!(split("5\"x,1", ','), Ok(vec!["5\"x".into(), "1".into()]));
+        assert_eq!(split("a;\"b;c\"", ';'), Ok(vec!["a".into(), "b;c".into()]));
+        assert_eq!(
+            split("u1,\"open", ','),
+            Err("a quoted field is not closed".to_string())
+        );
+    }
+
+    #[test]
+    fn columns_can_be_chosen_by_position_without_a_header() {
+        let data = "control;u1;1\ncontrol;u2;0\ntreatment;u3;1\n";
+        let options = CsvOptions::default()
+            .delimiter(';')
+            .header(false)
+            .user(1)
+            .variant(0)
+            .converted(2)
+            .revenue(None);
+        let events = read_events(data.as_bytes(), &options).unwrap();
+        assert_eq!(events.rows, 3);
+        assert_eq!(events.arms(), vec![Arm::new(1, 2), Arm::new(1, 1)]);
+        assert!(events.variants[0].revenue.is_none());
+
+        // Names need a header, and a header needs the named columns.
+        let options = CsvOptions::default().header(false);
+        assert_eq!(
+            read_events(data.as_bytes(), &options),
+            Err(Error::Csv {
+                line: 1,
+                message: "column 'user_id' is named, but the file has no header".to_string(),
+            })
+        );
+        let data = "\u{feff}user_id,variant,converted\nu1,control,1\n";
+        let options = CsvOptions::default().delimiter(',');
+        assert_eq!(
+            read_events(data.as_bytes(), &options),
+            Err(Error::Csv {
+                line: 1,
+                message: "the header has no column 'revenue'".to_string(),
+            })
+        );
+        let events = read_events(data.as_bytes(), &options.revenue(None)).unwrap();
+        assert_eq!(events.arms(), vec![Arm::new(1, 1)]);
+    }
+
+    #[test]
+    fn short_rows_are_reported() {
+        let data = "user_id,variant,converted,revenue\nu1,control\n,control,1,\n";
+        let events = read_events(data.as_bytes(), &CsvOptions::default()).unwrap();
+        let reasons: Vec<&str> = events.malformed.iter().map(|r| r.reason.as_str()).collect();
+        assert_eq!(
+            reasons,
+            [
+                "expected at least 4 fields, found 2",
+                "the user id is empty"
+            ]
+        );
+        assert!(events.variants.is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+//! The beta distribution.
+
+use crate::special::{beta_inc, ln_beta};
+
+/// Probability density function of the beta distribution with shape
+/// parameters `a` and `b`.
+pub fn pdf(x: f64, a: f64, b: f64) -> f64 {
+    if !(0.0..=1.0).contains(&x) {
+        return 0.0;
+    }
+    ((a - 1.0) * x.ln() + (b - 1.0) * (-x).ln_1p() - ln_beta(a, b)).exp()
+}
+
+/// Cumulative distribution function of the beta distribution.
+pub fn cdf(x: f64, a: f64, b: f64) -> f64 {
+    beta_inc(a, b, x)
+}
+
+/// Quantile function (inverse CDF) of the beta distribution.
+///
+/// Newton's method on the CDF, safeguarded by bisection so that it cannot
+/// leave the bracket around the root.
+pub fn quantile(p: f64, a: f64, b: f64) -> f64 {
+    if p.is_nan() || !(0.0..=1.0).contains(&p) {
+        return f64::NAN;
+    }
+    if p == 0.0 {
+        return 0.0;
+    }
+    if p == 1.0 {
+        return 1.0;
+    }
+    let (mut low, mut high) = (0.0, 1.0);
+    let mut x = a / (a + b);
+    for _ in 0..200 {
+        let error = cdf(x, a, b) - p;
+        if error.abs() < 1e-15 {
+            break;
+        }
+        if error < 0.0 {
+            low = x;
+        } else {
+            high = x;
+        }
+        let density = pdf(x, a, b);
+        let newton = x - error / density;
+        x = if density > 0.0 && newton > low && newton < high {
+            newton
+        } else {
+            0.5 * (low + high)
+        };
+        if high - low < 1e-16 {
+            break;
+        }
+    }
+    x
+}
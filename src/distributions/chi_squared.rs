@@ -0,0 +1,21 @@
+//! The chi-squared distribution.
+
+use crate::special::{gamma_p, gamma_q};
+
+/// Cumulative distribution function of the chi-squared distribution with
+/// `df` degrees of freedom.
+pub fn cdf(x: f64, df: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    gamma_p(df / 2.0, x / 2.0)
+}
+
+/// Survival function `1 - cdf(x, df)`, the p-value of a chi-squared
+/// statistic `x`.
+pub fn sf(x: f64, df: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    gamma_q(df / 2.0, x / 2.0)
+}
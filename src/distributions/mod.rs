@@ -0,0 +1,6 @@
+//! Probability distributions used by the hypothesis tests in this crate.
+
+pub mod beta;
+pub mod chi_squared;
+pub mod normal;
+pub mod student_t;
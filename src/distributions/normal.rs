@@ -0,0 +1,141 @@
+//! The standard normal distribution.
+
+use crate::special::erfc;
+
+/// Cumulative distribution function of the standard normal distribution.
+pub fn cdf(x: f64) -> f64 {
+    0.5 * erfc(-x / std::f64::consts::SQRT_2)
+}
+
+/// Survival function `1 - cdf(x)` of the standard normal distribution.
+///
+/// Computed directly rather than by subtraction, so it stays accurate for
+/// the tiny upper-tail probabilities reported as p-values.
+pub fn sf(x: f64) -> f64 {
+    0.5 * erfc(x / std::f64::consts::SQRT_2)
+}
+
+/// Probability density function of the standard normal distribution.
+pub fn pdf(x: f64) -> f64 {
+    (-0.5 * x * x).exp() / (2.0 * std::f64::consts::PI).sqrt()
+}
+
+/// Quantile function (inverse CDF) of the standard normal distribution.
+///
+/// Implements Wichura's algorithm AS 241 (PPND16), which is accurate to about
+/// 1 part in 10^16. Returns `-inf` and `+inf` at 0 and 1 and `NaN` outside
+/// `[0, 1]`.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::distributions::normal::quantile;
+///
+/// // Reference values from a 30-digit evaluation of `sqrt(2) * erfinv(2p - 1)`.
+/// assert!((quantile(0.975) - 1.959963984540054).abs() < 1e-14);
+/// assert!((quantile(1e-10) + 6.361340902404056).abs() < 1e-12);
+/// assert_eq!(quantile(0.5), 0.0);
+/// assert_eq!(quantile(1.0), f64::INFINITY);
+/// assert!(quantile(1.5).is_nan());
+/// ```
+#[allow(clippy::excessive_precision)]
+pub fn quantile(p: f64) -> f64 {
+    if p.is_nan() || !(0.0..=1.0).contains(&p) {
+        return f64::NAN;
+    }
+    if p == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p == 1.0 {
+        return f64::INFINITY;
+    }
+    let q = p - 0.5;
+    if q.abs() <= 0.425 {
+        let r = 0.180_625 - q * q;
+        return q
+            * (((((((2_509.080_928_730_122_7 * r + 33_430.575_583_588_128) * r
+                + 67_265.770_927_008_7)
+                * r
+                + 45_921.953_931_549_87)
+                * r
+                + 13_731.693_765_509_461)
+                * r
+                + 1_971.590_950_306_551_3)
+                * r
+                + 133.141_667_891_784_38)
+                * r
+                + 3.387_132_872_796_366_5)
+            / (((((((5_226.495_278_852_545 * r + 28_729.085_735_721_943) * r
+                + 39_307.895_800_092_71)
+                * r
+                + 21_213.794_301_586_597)
+                * r
+                + 5_394.196_021_424_751)
+                * r
+                + 687.187_007_492_057_9)
+                * r
+                + 42.313_330_701_600_91)
+                * r
+                + 1.0);
+    }
+    let tail = if q < 0.0 { p } else { 1.0 - p };
+    let mut r = (-tail.ln()).sqrt();
+    let value = if r <= 5.0 {
+        r -= 1.6;
+        (((((((7.745_450_142_783_414e-4 * r + 2.272_384_498_926_918_4e-2) * r
+            + 2.417_807_251_774_506e-1)
+            * r
+            + 1.270_458_252_452_368_4)
+            * r
+            + 3.647_848_324_763_204_5)
+            * r
+            + 5.769_497_221_460_691)
+            * r
+            + 4.630_337_846_156_546)
+            * r
+            + 1.423_437_110_749_683_5)
+            / (((((((1.050_750_071_644_416_9e-9 * r + 5.475_938_084_995_345e-4) * r
+                + 1.519_866_656_361_645_7e-2)
+                * r
+                + 1.481_039_764_274_800_8e-1)
+                * r
+                + 6.897_673_349_851e-1)
+                * r
+                + 1.676_384_830_183_803_8)
+                * r
+                + 2.053_191_626_637_758_8)
+                * r
+                + 1.0)
+    } else {
+        r -= 5.0;
+        (((((((2.010_334_399_292_288_1e-7 * r + 2.711_555_568_743_487_6e-5) * r
+            + 1.242_660_947_388_078_4e-3)
+            * r
+            + 2.653_218_952_657_612_4e-2)
+            * r
+            + 2.965_605_718_285_048_7e-1)
+            * r
+            + 1.784_826_539_917_291_3)
+            * r
+            + 5.463_784_911_164_114_4)
+            * r
+            + 6.657_904_643_501_103)
+            / (((((((2.044_263_103_389_939_7e-15 * r + 1.421_511_758_316_446e-7) * r
+                + 1.846_318_317_510_054_8e-5)
+                * r
+                + 7.868_691_311_456_133e-4)
+                * r
+                + 1.487_536_129_085_061_5e-2)
+                * r
+                + 1.369_298_809_227_358e-1)
+                * r
+                + 5.998_322_065_558_88e-1)
+                * r
+                + 1.0)
+    };
+    if q < 0.0 {
+        -value
+    } else {
+        value
+    }
+}
@@ -0,0 +1,67 @@
+//! Student's t distribution.
+
+use super::beta;
+use crate::special::{beta_inc, ln_beta};
+
+/// Probability density function of Student's t distribution with `df`
+/// degrees of freedom.
+pub fn pdf(t: f64, df: f64) -> f64 {
+    (-0.5 * (df + 1.0) * (t * t / df).ln_1p() - 0.5 * df.ln() - ln_beta(0.5 * df, 0.5)).exp()
+}
+
+/// Cumulative distribution function of Student's t distribution.
+///
+/// Uses the identity `P(|T| > t) = I_x(df / 2, 1 / 2)` with
+/// `x = df / (df + t^2)`.
+pub fn cdf(t: f64, df: f64) -> f64 {
+    if t.is_nan() {
+        return f64::NAN;
+    }
+    let tail = 0.5 * beta_inc(0.5 * df, 0.5, df / (df + t * t));
+    if t > 0.0 {
+        1.0 - tail
+    } else {
+        tail
+    }
+}
+
+/// Survival function `1 - cdf(t, df)`, computed without cancellation in the
+/// upper tail.
+pub fn sf(t: f64, df: f64) -> f64 {
+    cdf(-t, df)
+}
+
+/// Quantile function (inverse CDF) of Student's t distribution.
+///
+/// Starts from the beta quantile behind the CDF and polishes the result with
+/// Newton's method on the t scale, where it no longer suffers from
+/// cancellation near the centre.
+pub fn quantile(p: f64, df: f64) -> f64 {
+    if p.is_nan() || !(0.0..=1.0).contains(&p) {
+        return f64::NAN;
+    }
+    if p == 0.0 {
+        return f64::NEG_INFINITY;
+    }
+    if p == 1.0 {
+        return f64::INFINITY;
+    }
+    if p == 0.5 {
+        return 0.0;
+    }
+    let lower = p.min(1.0 - p);
+    let x = beta::quantile(2.0 * lower, 0.5 * df, 0.5);
+    let mut t = -(df * (1.0 - x) / x).sqrt();
+    for _ in 0..4 {
+        let density = pdf(t, df);
+        if density <= 0.0 || !density.is_finite() {
+            break;
+        }
+        t -= (cdf(t, df) - lower) / density;
+    }
+    if p > 0.5 {
+        -t
+    } else {
+        t
+    }
+}
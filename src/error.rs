@@ -0,0 +1,114 @@
+//! The error type shared by every test in this crate.
+
+use std::fmt;
+
+/// Reasons a test cannot be carried out on the inputs it was given.
+///
+/// A test that runs but finds no significant difference is not an error; it
+/// reports its statistics through the usual result type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// A proportion was outside `[0, 1]` or was not a number.
+    InvalidProportion(f64),
+    /// Both groups converted at a rate of exactly 0 or exactly 1, so the
+    /// test statistic has no variance to be scaled by.
+    ZeroVariance,
+    /// A group had fewer samples than the test needs.
+    InsufficientSample {
+        /// The smallest sample size the test accepts.
+        required: u64,
+        /// The sample size that was supplied.
+        actual: u64,
+    },
+    /// A group had more samples than the test can handle in reasonable time
+    /// and memory.
+    SampleTooLarge {
+        /// The largest sample size the test accepts.
+        limit: u64,
+        /// The sample size that was supplied.
+        actual: u64,
+    },
+    /// A test comparing several groups was given too few of them.
+    TooFewGroups {
+        /// The smallest number of groups the test accepts.
+        required: usize,
+        /// The number of groups that was supplied.
+        actual: usize,
+    },
+    /// A group reported more successes than trials.
+    SuccessesExceedTrials {
+        /// The number of successes supplied.
+        successes: u64,
+        /// The number of trials supplied.
+        trials: u64,
+    },
+    /// Combining the sample sizes overflowed.
+    Overflow,
+    /// A tuning parameter such as a significance level was out of range.
+    InvalidParameter {
+        /// The name of the parameter.
+        name: &'static str,
+        /// The value that was supplied.
+        value: f64,
+    },
+    /// The arm sizes are implausible under the intended traffic split, and
+    /// the options asked to block the result.
+    SampleRatioMismatch {
+        /// The p-value of the sample ratio mismatch check.
+        p_value: f64,
+    },
+    /// A CSV file could not be read.
+    Csv {
+        /// The line of the file, counting from one.
+        line: u64,
+        /// What went wrong.
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidProportion(p) => write!(f, "proportion {} is not within [0, 1]", p),
+            Error::ZeroVariance => write!(
+                f,
+                "both conversion rates are 0 or 1, so the variance is zero"
+            ),
+            Error::InsufficientSample { required, actual } => write!(
+                f,
+                "insufficient sample size: {} samples supplied, at least {} required",
+                actual, required
+            ),
+            Error::SampleTooLarge { limit, actual } => write!(
+                f,
+                "sample too large: {} samples supplied, at most {} supported",
+                actual, limit
+            ),
+            Error::TooFewGroups { required, actual } => write!(
+                f,
+                "{} groups supplied, at least {} required",
+                actual, required
+            ),
+            Error::SuccessesExceedTrials { successes, trials } => write!(
+                f,
+                "{} successes reported out of only {} trials",
+                successes, trials
+            ),
+            Error::Overflow => write!(f, "sample sizes overflowed when combined"),
+            Error::InvalidParameter { name, value } => {
+                write!(f, "invalid value {} for parameter `{}`", value, name)
+            }
+            Error::SampleRatioMismatch { p_value } => write!(
+                f,
+                "sample ratio mismatch: the arm sizes do not match the intended split (p = {:.2e})",
+                p_value
+            ),
+            Error::Csv { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A specialised `Result` type for this crate.
+pub type Result<T> = std::result::Result<T, Error>;
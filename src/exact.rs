@@ -0,0 +1,540 @@
+//! Exact tests on the 2x2 table of conversions, for experiments too small
+//! for the normal approximation behind [`ab_conversion_test`].
+//!
+//! The table has one row per variant and columns for units that did and did
+//! not convert. Odds ratios compare B to A, so a value above 1 means version
+//! B converts better.
+//!
+//! [`ab_conversion_test`]: crate::conversion::ab_conversion_test
+
+use crate::conversion::{Alternative, Arm, TestOptions, Variant};
+use crate::error::{Error, Result};
+use crate::special::ln_choose;
+
+/// Relative tolerance under which two tables count as equally extreme, so
+/// that rounding does not exclude tables tied with the observed one.
+const TIE_TOLERANCE: f64 = 1e-7;
+
+/// The most units an arm may have in Barnard's and Boschloo's tests, whose
+/// cost grows with the product of the arm sizes.
+pub const UNCONDITIONAL_MAX_TRIALS: u64 = 5000;
+
+/// The outcome of [`fisher_exact`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FisherTestResult {
+    /// The variant favoured by a significant result, or `None`.
+    pub winner: Option<Variant>,
+    /// The p-value under the alternative in the test options; the mid-p value
+    /// if the options ask for it.
+    pub p_value: f64,
+    /// The conditional maximum likelihood estimate of the odds ratio.
+    pub odds_ratio: f64,
+    /// Lower bound of the confidence interval for the odds ratio.
+    pub ci_low: f64,
+    /// Upper bound of the confidence interval for the odds ratio.
+    pub ci_high: f64,
+}
+
+/// The outcome of [`barnard_exact`] and [`boschloo_exact`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnconditionalTestResult {
+    /// The variant favoured by a significant result, or `None`.
+    pub winner: Option<Variant>,
+    /// The observed value of the test statistic.
+    pub statistic: f64,
+    /// The p-value, maximised over the common conversion rate.
+    pub p_value: f64,
+    /// The common conversion rate at which the p-value is attained.
+    pub nuisance: f64,
+}
+
+/// The distribution of B's successes given the total number of successes,
+/// a Fisher noncentral hypergeometric distribution.
+struct Conditional {
+    /// The smallest possible count.
+    low: u64,
+    /// The observed count.
+    observed: u64,
+    /// Log of the central probability weights, one per possible count.
+    ln_weights: Vec<f64>,
+}
+
+impl Conditional {
+    fn new(a: Arm, b: Arm) -> Self {
+        let total = a.successes + b.successes;
+        let low = total.saturating_sub(a.trials);
+        let high = total.min(b.trials);
+        let ln_weights = (low..=high)
+            .map(|x| {
+                ln_choose(b.trials as f64, x as f64)
+                    + ln_choose(a.trials as f64, (total - x) as f64)
+            })
+            .collect();
+        Conditional {
+            low,
+            observed: b.successes,
+            ln_weights,
+        }
+    }
+
+    fn high(&self) -> u64 {
+        self.low + self.ln_weights.len() as u64 - 1
+    }
+
+    fn index(&self) -> usize {
+        (self.observed - self.low) as usize
+    }
+
+    /// Probabilities of each count when the odds ratio is `exp(ln_psi)`.
+    fn probabilities(&self, ln_psi: f64) -> Vec<f64> {
+        let logs: Vec<f64> = self
+            .ln_weights
+            .iter()
+            .enumerate()
+            .map(|(i, w)| w + i as f64 * ln_psi)
+            .collect();
+        let max = logs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = logs.iter().map(|l| (l - max).exp()).collect();
+        let sum: f64 = weights.iter().sum();
+        weights.iter().map(|w| w / sum).collect()
+    }
+
+    /// `P(X >= x)` and `P(X <= x)` at the observed count, with the observed
+    /// count itself weighted by one half for mid-p values.
+    fn tails(&self, ln_psi: f64, mid_p: bool) -> (f64, f64) {
+        let probs = self.probabilities(ln_psi);
+        let i = self.index();
+        let weight = if mid_p { 0.5 } else { 1.0 };
+        let upper = probs[i + 1..].iter().sum::<f64>() + weight * probs[i];
+        let lower = probs[..i].iter().sum::<f64>() + weight * probs[i];
+        (upper.min(1.0), lower.min(1.0))
+    }
+
+    /// The two-sided p-value that sums every count no more likely than the
+    /// observed one.
+    fn two_sided(&self, mid_p: bool) -> f64 {
+        let probs = self.probabilities(0.0);
+        let observed = probs[self.index()] * (1.0 + TIE_TOLERANCE);
+        let (mut less, mut tied) = (0.0, 0.0);
+        for &p in &probs {
+            if p * (1.0 + TIE_TOLERANCE) < probs[self.index()] {
+                less += p;
+            } else if p <= observed {
+                tied += p;
+            }
+        }
+        let weight = if mid_p { 0.5 } else { 1.0 };
+        (less + weight * tied).min(1.0)
+    }
+
+    /// The log odds ratio at which `f` falls to `target`, for `f` decreasing
+    /// in the log odds ratio.
+    fn solve(&self, target: f64, f: impl Fn(f64) -> f64) -> f64 {
+        let (mut below, mut above) = (-100.0, 100.0);
+        for _ in 0..200 {
+            let mid = 0.5 * (below + above);
+            if f(mid) > target {
+                below = mid;
+            } else {
+                above = mid;
+            }
+        }
+        0.5 * (below + above)
+    }
+
+    /// The conditional maximum likelihood estimate of the odds ratio, which
+    /// matches the conditional mean to the observed count.
+    fn estimate(&self) -> f64 {
+        if self.observed == self.low {
+            return 0.0;
+        }
+        if self.observed == self.high() {
+            return f64::INFINITY;
+        }
+        let mean = |ln_psi: f64| {
+            let probs = self.probabilities(ln_psi);
+            probs
+                .iter()
+                .enumerate()
+                .map(|(i, p)| i as f64 * p)
+                .sum::<f64>()
+        };
+        let ln_psi = self.solve(-(self.index() as f64), |t| -mean(t));
+        ln_psi.exp()
+    }
+
+    /// The lower confidence bound whose upper tail probability is `alpha`.
+    fn lower_bound(&self, alpha: f64, mid_p: bool) -> f64 {
+        if self.observed == self.low {
+            return 0.0;
+        }
+        self.solve(-alpha, |t| -self.tails(t, mid_p).0).exp()
+    }
+
+    /// The upper confidence bound whose lower tail probability is `alpha`.
+    fn upper_bound(&self, alpha: f64, mid_p: bool) -> f64 {
+        if self.observed == self.high() {
+            return f64::INFINITY;
+        }
+        self.solve(alpha, |t| self.tails(t, mid_p).1).exp()
+    }
+
+    /// The confidence interval for the odds ratio set by `options`.
+    fn interval(&self, options: &TestOptions) -> (f64, f64) {
+        let mid_p = options.mid_p;
+        match options.alternative {
+            Alternative::TwoSided => {
+                let tail = (1.0 - options.confidence) / 2.0;
+                (self.lower_bound(tail, mid_p), self.upper_bound(tail, mid_p))
+            }
+            Alternative::Greater => (
+                self.lower_bound(1.0 - options.confidence, mid_p),
+                f64::INFINITY,
+            ),
+            Alternative::Less => (0.0, self.upper_bound(1.0 - options.confidence, mid_p)),
+        }
+    }
+}
+
+/// The exact conditional confidence interval for the odds ratio of B
+/// against A, on arms the caller has validated.
+pub(crate) fn odds_ratio_interval(a: Arm, b: Arm, options: &TestOptions) -> (f64, f64) {
+    Conditional::new(a, b).interval(options)
+}
+
+/// Fisher's exact test of equal conversion rates.
+///
+/// The test conditions on the total number of conversions, under which B's
+/// conversions follow a hypergeometric distribution when the rates are
+/// equal. The two-sided p-value sums the probabilities of every table no
+/// more likely than the observed one. With `options.mid_p` set, tables as
+/// likely as the observed one count for half, which removes most of the
+/// test's conservatism; the confidence interval then inverts the mid-p tails
+/// as well.
+///
+/// # Errors
+///
+/// Returns [`Error::SuccessesExceedTrials`] for an inconsistent arm,
+/// [`Error::InsufficientSample`] if an arm has no trials, and
+/// [`Error::InvalidParameter`] for invalid options.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::{Arm, TestOptions};
+/// use statistical_computing::exact::fisher_exact;
+///
+/// // 3 of 2000 against 12 of 2000.
+/// let (a, b) = (Arm::new(3, 2000), Arm::new(12, 2000));
+/// let result = fisher_exact(a, b, &TestOptions::default()).unwrap();
+/// assert!((result.p_value - 0.0348).abs() < 1e-4);
+/// assert!(result.ci_low > 1.0);
+/// ```
+pub fn fisher_exact(a: Arm, b: Arm, options: &TestOptions) -> Result<FisherTestResult> {
+    check_arms(a, b, options)?;
+    let conditional = Conditional::new(a, b);
+    let mid_p = options.mid_p;
+    let (upper, lower) = conditional.tails(0.0, mid_p);
+    let p_value = match options.alternative {
+        Alternative::TwoSided => conditional.two_sided(mid_p),
+        Alternative::Greater => upper,
+        Alternative::Less => lower,
+    };
+    let (ci_low, ci_high) = conditional.interval(options);
+    Ok(FisherTestResult {
+        winner: winner(options, p_value, b.rate() > a.rate()),
+        p_value,
+        odds_ratio: conditional.estimate(),
+        ci_low,
+        ci_high,
+    })
+}
+
+/// Barnard's unconditional exact test of equal conversion rates.
+///
+/// Unlike Fisher's test, the total number of conversions is not held fixed.
+/// Tables are ordered by the pooled z statistic of [`ab_conversion_test`],
+/// and the p-value is the largest probability of a table at least as extreme
+/// as the observed one over every common conversion rate. The test is more
+/// powerful than Fisher's. Ranking every table costs `O(n1 * n2)` once,
+/// after which each candidate rate costs `O(n1 + n2)`; arms of a few
+/// thousand units take well under a second. Arms larger than
+/// [`UNCONDITIONAL_MAX_TRIALS`] are refused and are better served by
+/// [`ab_conversion_test`].
+///
+/// The p-value is always the full exact one; `options.mid_p` does not
+/// apply.
+///
+/// # Errors
+///
+/// Returns the same errors as [`fisher_exact`], and
+/// [`Error::SampleTooLarge`] if an arm has more than
+/// [`UNCONDITIONAL_MAX_TRIALS`] units.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::{Arm, TestOptions};
+/// use statistical_computing::exact::{barnard_exact, fisher_exact};
+/// use statistical_computing::Error;
+///
+/// let (a, b) = (Arm::new(1, 12), Arm::new(7, 12));
+/// let options = TestOptions::default();
+/// let barnard = barnard_exact(a, b, &options).unwrap();
+/// assert!(barnard.p_value < fisher_exact(a, b, &options).unwrap().p_value);
+///
+/// // 3 of 2000 against 12 of 2000.
+/// let barnard = barnard_exact(Arm::new(3, 2000), Arm::new(12, 2000), &options).unwrap();
+/// assert!((barnard.p_value - 0.0202).abs() < 1e-4);
+///
+/// // Arms this large are left to the normal approximation.
+/// let large = Arm::new(5000, 100_000);
+/// assert!(matches!(
+///     barnard_exact(large, large, &options),
+///     Err(Error::SampleTooLarge { limit: 5000, actual: 100_000 })
+/// ));
+/// ```
+///
+/// [`ab_conversion_test`]: crate::conversion::ab_conversion_test
+pub fn barnard_exact(a: Arm, b: Arm, options: &TestOptions) -> Result<UnconditionalTestResult> {
+    check_unconditional_arms(a, b, options)?;
+    let (n1, n2) = (a.trials as f64, b.trials as f64);
+    let statistic = |x1: u64, x2: u64| {
+        let (p1, p2) = (x1 as f64 / n1, x2 as f64 / n2);
+        let p = (x1 + x2) as f64 / (n1 + n2);
+        let se = (p * (1.0 - p) * (1.0 / n1 + 1.0 / n2)).sqrt();
+        if se > 0.0 {
+            (p2 - p1) / se
+        } else {
+            0.0
+        }
+    };
+    let table = |x1: u64, x2: u64| match options.alternative {
+        Alternative::TwoSided => statistic(x1, x2).abs(),
+        Alternative::Greater => statistic(x1, x2),
+        Alternative::Less => -statistic(x1, x2),
+    };
+    unconditional(a, b, options, statistic(a.successes, b.successes), table)
+}
+
+/// Boschloo's unconditional exact test of equal conversion rates.
+///
+/// This is Barnard's construction with tables ordered by their Fisher exact
+/// p-value instead of the z statistic. It is uniformly more powerful than
+/// Fisher's test. The reported statistic is the observed table's Fisher
+/// p-value. The p-values of all `(n1 + 1) * (n2 + 1)` tables are held in
+/// memory, which is the practical limit: arms of 2000 units need 32 MB and
+/// a fraction of a second, and arms of [`UNCONDITIONAL_MAX_TRIALS`] units,
+/// the most accepted, need 200 MB.
+///
+/// As with Barnard's test, `options.mid_p` does not apply: both the Fisher
+/// p-values that order the tables and the reported p-value are full ones.
+///
+/// # Errors
+///
+/// Returns the same errors as [`barnard_exact`].
+pub fn boschloo_exact(a: Arm, b: Arm, options: &TestOptions) -> Result<UnconditionalTestResult> {
+    check_unconditional_arms(a, b, options)?;
+    let (n1, n2) = (a.trials as usize, b.trials as usize);
+    let ln_c1 = ln_binomials(a.trials);
+    let ln_c2 = ln_binomials(b.trials);
+    // Every table with the same total number of conversions shares one
+    // conditional distribution, so each is built once and the Fisher p-value
+    // of each of its tables read off it. Tables with smaller p-values are
+    // more extreme.
+    let mut cache = vec![vec![f64::NAN; n2 + 1]; n1 + 1];
+    for total in 0..=n1 + n2 {
+        let low = total.saturating_sub(n1);
+        let high = total.min(n2);
+        let logs: Vec<f64> = (low..=high)
+            .map(|x2| ln_c2[x2] + ln_c1[total - x2])
+            .collect();
+        let max = logs.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let weights: Vec<f64> = logs.iter().map(|l| (l - max).exp()).collect();
+        let sum: f64 = weights.iter().sum();
+        let probs: Vec<f64> = weights.iter().map(|w| w / sum).collect();
+        let p_values: Vec<f64> = match options.alternative {
+            Alternative::Greater => {
+                let mut tail = 0.0;
+                let mut p_values: Vec<f64> = probs
+                    .iter()
+                    .rev()
+                    .map(|p| {
+                        tail += p;
+                        tail
+                    })
+                    .collect();
+                p_values.reverse();
+                p_values
+            }
+            Alternative::Less => {
+                let mut tail = 0.0;
+                probs
+                    .iter()
+                    .map(|p| {
+                        tail += p;
+                        tail
+                    })
+                    .collect()
+            }
+            Alternative::TwoSided => {
+                // The sum of every probability no larger than each table's.
+                let mut sorted = probs.clone();
+                sorted.sort_by(f64::total_cmp);
+                let mut cumulative = Vec::with_capacity(sorted.len());
+                let mut running = 0.0;
+                for p in &sorted {
+                    running += p;
+                    cumulative.push(running);
+                }
+                probs
+                    .iter()
+                    .map(|p| {
+                        let count = sorted.partition_point(|q| *q <= p * (1.0 + TIE_TOLERANCE));
+                        cumulative[count - 1]
+                    })
+                    .collect()
+            }
+        };
+        for (x2, p_value) in (low..=high).zip(p_values) {
+            cache[total - x2][x2] = -p_value.min(1.0);
+        }
+    }
+    let observed = -cache[a.successes as usize][b.successes as usize];
+    unconditional(a, b, options, observed, |x1, x2| {
+        cache[x1 as usize][x2 as usize]
+    })
+}
+
+/// `ln C(n, x)` for every `x` from 0 to `n`.
+fn ln_binomials(n: u64) -> Vec<f64> {
+    (0..=n).map(|x| ln_choose(n as f64, x as f64)).collect()
+}
+
+/// Maximises the probability of tables at least as extreme as the observed
+/// one over the common conversion rate, where `extremity` grows with how
+/// strongly a table favours the alternative.
+fn unconditional(
+    a: Arm,
+    b: Arm,
+    options: &TestOptions,
+    statistic: f64,
+    extremity: impl Fn(u64, u64) -> f64,
+) -> Result<UnconditionalTestResult> {
+    let observed = extremity(a.successes, b.successes);
+    let threshold = observed - TIE_TOLERANCE * observed.abs().max(1.0);
+    let mut extreme = Vec::new();
+    for x1 in 0..=a.trials {
+        for x2 in 0..=b.trials {
+            if extremity(x1, x2) >= threshold {
+                extreme.push((x1, x2));
+            }
+        }
+    }
+    let ln_c1 = ln_binomials(a.trials);
+    let ln_c2 = ln_binomials(b.trials);
+    // A table's probability depends on the rate only through its total
+    // conversions, so the binomial coefficients of the extreme tables are
+    // summed once per total and each candidate rate costs O(n1 + n2).
+    let totals = (a.trials + b.trials) as usize + 1;
+    let mut ln_max = vec![f64::NEG_INFINITY; totals];
+    for &(x1, x2) in &extreme {
+        let total = (x1 + x2) as usize;
+        ln_max[total] = ln_max[total].max(ln_c1[x1 as usize] + ln_c2[x2 as usize]);
+    }
+    let mut scaled = vec![0.0; totals];
+    for &(x1, x2) in &extreme {
+        let total = (x1 + x2) as usize;
+        scaled[total] += (ln_c1[x1 as usize] + ln_c2[x2 as usize] - ln_max[total]).exp();
+    }
+    let ln_weights: Vec<(f64, f64)> = ln_max
+        .iter()
+        .zip(&scaled)
+        .enumerate()
+        .filter(|(_, (_, &s))| s > 0.0)
+        .map(|(total, (m, s))| (total as f64, m + s.ln()))
+        .collect();
+    let n = (a.trials + b.trials) as f64;
+    let probability = |rate: f64| {
+        let (ln_p, ln_q) = (rate.ln(), (1.0 - rate).ln());
+        ln_weights
+            .iter()
+            .map(|&(total, ln_w)| (ln_w + total * ln_p + (n - total) * ln_q).exp())
+            .sum::<f64>()
+    };
+
+    // A coarse grid locates the global maximum, then golden-section search
+    // refines it within the neighbouring grid cells.
+    const GRID: usize = 200;
+    let mut best = (0.5, probability(0.5));
+    for i in 1..GRID {
+        let rate = i as f64 / GRID as f64;
+        let value = probability(rate);
+        if value > best.1 {
+            best = (rate, value);
+        }
+    }
+    let step = 1.0 / GRID as f64;
+    let (mut left, mut right) = ((best.0 - step).max(1e-12), (best.0 + step).min(1.0 - 1e-12));
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    for _ in 0..60 {
+        let c = right - ratio * (right - left);
+        let d = left + ratio * (right - left);
+        if probability(c) > probability(d) {
+            right = d;
+        } else {
+            left = c;
+        }
+    }
+    let refined = 0.5 * (left + right);
+    let value = probability(refined);
+    if value > best.1 {
+        best = (refined, value);
+    }
+    let p_value = best.1.min(1.0);
+    Ok(UnconditionalTestResult {
+        winner: winner(options, p_value, b.rate() > a.rate()),
+        statistic,
+        p_value,
+        nuisance: best.0,
+    })
+}
+
+fn check_arms(a: Arm, b: Arm, options: &TestOptions) -> Result<()> {
+    options.validate()?;
+    a.validate()?;
+    b.validate()?;
+    let smallest = a.trials.min(b.trials);
+    if smallest == 0 {
+        return Err(Error::InsufficientSample {
+            required: 1,
+            actual: smallest,
+        });
+    }
+    Ok(())
+}
+
+fn check_unconditional_arms(a: Arm, b: Arm, options: &TestOptions) -> Result<()> {
+    check_arms(a, b, options)?;
+    let largest = a.trials.max(b.trials);
+    if largest > UNCONDITIONAL_MAX_TRIALS {
+        return Err(Error::SampleTooLarge {
+            limit: UNCONDITIONAL_MAX_TRIALS,
+            actual: largest,
+        });
+    }
+    Ok(())
+}
+
+/// The variant a p-value below `alpha` points to.
+fn winner(options: &TestOptions, p_value: f64, b_better: bool) -> Option<Variant> {
+    if p_value >= options.alpha {
+        return None;
+    }
+    match options.alternative {
+        Alternative::TwoSided if b_better => Some(Variant::B),
+        Alternative::TwoSided => Some(Variant::A),
+        Alternative::Greater => Some(Variant::B),
+        Alternative::Less => Some(Variant::A),
+    }
+}
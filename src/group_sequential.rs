@@ -0,0 +1,635 @@
+//! Group sequential designs with a pre-planned number of interim analyses.
+//!
+//! A design fixes, before the experiment starts, the fractions of the final
+//! sample at which it will be analysed and how the type I error `alpha` is
+//! spent across those looks. Each look then compares the z statistic of
+//! [`ab_conversion_test`] with an efficacy boundary and, optionally, a
+//! futility boundary.
+//!
+//! Boundaries are found by the recursive numerical integration of Armitage,
+//! McPherson and Rowe: the sub-density of the z statistic over the region
+//! where the experiment continues is carried from one look to the next on a
+//! Simpson's rule grid, and each boundary is solved so that the probability
+//! of first crossing it matches the error spent at that look.
+//!
+//! [`ab_conversion_test`]: crate::conversion::ab_conversion_test
+
+use crate::conversion::{
+    ab_conversion_test_counts, Alternative, Arm, ConversionTestResult, TestOptions, Variant,
+};
+use crate::distributions::normal;
+use crate::error::{Error, Result};
+
+/// Spacing of the integration grid on the z scale.
+const GRID_STEP: f64 = 0.05;
+/// How far below the mean the continuation region is truncated when there
+/// is no futility boundary.
+const TAIL: f64 = 8.0;
+
+/// A function describing how an error probability is spent over the
+/// information fraction `t` in `[0, 1]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Spending {
+    /// Lan & DeMets' O'Brien-Fleming-type function, which spends almost
+    /// nothing early and keeps the final boundary close to a fixed-sample
+    /// test.
+    OBrienFleming,
+    /// Lan & DeMets' Pocock-type function, which spends evenly and gives
+    /// nearly constant boundaries.
+    Pocock,
+    /// Hwang, Shih & DeCani's family with parameter `gamma`; negative values
+    /// are conservative early, `-4` resembles O'Brien-Fleming and `1`
+    /// resembles Pocock. `gamma` must be finite.
+    HwangShihDeCani(f64),
+    /// Kim & DeMets' power family `t^rho`, for a finite `rho > 0`.
+    Power(f64),
+}
+
+impl Spending {
+    /// The cumulative error spent by information fraction `t` out of a total
+    /// of `level`.
+    pub fn spent(self, level: f64, t: f64) -> f64 {
+        if t <= 0.0 {
+            return 0.0;
+        }
+        if t >= 1.0 {
+            return level;
+        }
+        match self {
+            Spending::OBrienFleming => {
+                2.0 * normal::sf(normal::quantile(1.0 - level / 2.0) / t.sqrt())
+            }
+            Spending::Pocock => level * (1.0 + (std::f64::consts::E - 1.0) * t).ln(),
+            Spending::HwangShihDeCani(0.0) => level * t,
+            Spending::HwangShihDeCani(gamma) => {
+                level * (-(-gamma * t).exp_m1()) / (-(-gamma).exp_m1())
+            }
+            Spending::Power(rho) => level * t.powf(rho),
+        }
+    }
+
+    /// Checks that the parameter of the family, if any, is in range.
+    fn validate(self) -> Result<()> {
+        match self {
+            Spending::HwangShihDeCani(gamma) if !gamma.is_finite() => {
+                Err(Error::InvalidParameter {
+                    name: "gamma",
+                    value: gamma,
+                })
+            }
+            Spending::Power(rho) if !(rho > 0.0 && rho.is_finite()) => {
+                Err(Error::InvalidParameter {
+                    name: "rho",
+                    value: rho,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Settings for [`GroupSequentialDesign::new`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DesignOptions {
+    /// The overall significance level.
+    pub alpha: f64,
+    /// The alternative hypothesis. Two-sided designs use symmetric
+    /// boundaries, each spending `alpha / 2`.
+    pub alternative: Alternative,
+    /// How `alpha` is spent across the looks.
+    pub efficacy: Spending,
+    /// How the type II error is spent on a futility boundary, if there is
+    /// one. Futility boundaries are non-binding and need a one-sided
+    /// alternative.
+    pub futility: Option<Spending>,
+    /// The power the design is planned for.
+    pub power: f64,
+}
+
+impl Default for DesignOptions {
+    fn default() -> Self {
+        DesignOptions {
+            alpha: 0.05,
+            alternative: Alternative::TwoSided,
+            efficacy: Spending::OBrienFleming,
+            futility: None,
+            power: 0.8,
+        }
+    }
+}
+
+impl DesignOptions {
+    /// Sets the overall significance level.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the alternative hypothesis.
+    pub fn alternative(mut self, alternative: Alternative) -> Self {
+        self.alternative = alternative;
+        self
+    }
+
+    /// Sets the alpha spending function.
+    pub fn efficacy(mut self, efficacy: Spending) -> Self {
+        self.efficacy = efficacy;
+        self
+    }
+
+    /// Adds a futility boundary with the given beta spending function.
+    pub fn futility(mut self, futility: Spending) -> Self {
+        self.futility = Some(futility);
+        self
+    }
+
+    /// Sets the planned power.
+    pub fn power(mut self, power: f64) -> Self {
+        self.power = power;
+        self
+    }
+}
+
+/// What to do after an interim look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Decision {
+    /// Keep collecting data.
+    Continue,
+    /// Stop and ship the given variant.
+    Efficacy(Variant),
+    /// Stop without a winner. At the final look, anything short of efficacy
+    /// ends here.
+    Futility,
+}
+
+/// The evaluation of one look by [`GroupSequentialDesign::evaluate_counts`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterimResult {
+    /// The index of the look, starting at 0.
+    pub look: usize,
+    /// The decision the boundaries lead to.
+    pub decision: Decision,
+    /// The conversion test at this look, whose `z` was compared with the
+    /// boundaries.
+    pub test: ConversionTestResult,
+}
+
+/// A group sequential design with its boundaries on the z scale.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::Alternative;
+/// use statistical_computing::group_sequential::{Decision, DesignOptions, GroupSequentialDesign};
+///
+/// let options = DesignOptions::default().alternative(Alternative::Greater);
+/// let design = GroupSequentialDesign::new(&[0.25, 0.5, 0.75, 1.0], &options).unwrap();
+/// // O'Brien-Fleming-type boundaries start high and end near 1.645.
+/// assert!(design.efficacy[0] > 3.5);
+/// assert!((design.efficacy[3] - 1.715).abs() < 0.01);
+/// assert_eq!(design.evaluate(1, 2.5), Decision::Continue);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupSequentialDesign {
+    /// The information fraction of each look.
+    pub information: Vec<f64>,
+    /// The efficacy boundary of each look, in the direction of the
+    /// alternative; two-sided designs reject when `|z|` reaches it.
+    pub efficacy: Vec<f64>,
+    /// The futility boundary of each look, if the design has one.
+    pub futility: Option<Vec<f64>>,
+    /// The cumulative type I error spent by each look.
+    pub cumulative_alpha: Vec<f64>,
+    /// The expected z statistic at the final look under the alternative the
+    /// design is powered for.
+    pub drift: f64,
+    /// How much larger the maximum sample must be than a fixed-sample test
+    /// with the same level and power.
+    pub inflation_factor: f64,
+    alternative: Alternative,
+}
+
+impl GroupSequentialDesign {
+    /// Computes the boundaries for looks at the given information fractions.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if the fractions are not strictly
+    /// increasing within `(0, 1]` and ending at 1, if `alpha` or the power is
+    /// outside `(0, 1)`, if a spending function has a parameter out of range,
+    /// or if a futility boundary is requested for a two-sided design.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use statistical_computing::conversion::Alternative;
+    /// use statistical_computing::group_sequential::{DesignOptions, GroupSequentialDesign, Spending};
+    /// use statistical_computing::Error;
+    ///
+    /// let looks = [0.25, 0.5, 0.75, 1.0];
+    /// let options = DesignOptions::default().efficacy(Spending::Power(0.0));
+    /// assert_eq!(
+    ///     GroupSequentialDesign::new(&looks, &options),
+    ///     Err(Error::InvalidParameter { name: "rho", value: 0.0 })
+    /// );
+    /// let options = DesignOptions::default().efficacy(Spending::HwangShihDeCani(f64::NAN));
+    /// assert!(matches!(
+    ///     GroupSequentialDesign::new(&looks, &options),
+    ///     Err(Error::InvalidParameter { name: "gamma", .. })
+    /// ));
+    /// let options = DesignOptions::default()
+    ///     .alternative(Alternative::Greater)
+    ///     .futility(Spending::Power(-1.0));
+    /// assert_eq!(
+    ///     GroupSequentialDesign::new(&looks, &options),
+    ///     Err(Error::InvalidParameter { name: "rho", value: -1.0 })
+    /// );
+    /// ```
+    pub fn new(information: &[f64], options: &DesignOptions) -> Result<Self> {
+        validate(information, options)?;
+        let two_sided = options.alternative == Alternative::TwoSided;
+        let sides = if two_sided { 2.0 } else { 1.0 };
+
+        // Efficacy boundaries under the null, ignoring futility so that it
+        // stays non-binding.
+        let mut efficacy = Vec::with_capacity(information.len());
+        let mut cumulative_alpha = Vec::with_capacity(information.len());
+        let mut previous: Option<Stage> = None;
+        let mut t_previous = 0.0;
+        let mut spent = 0.0;
+        for &t in information {
+            let total = options.efficacy.spent(options.alpha / sides, t);
+            let target = total - spent;
+            let bound = solve_decreasing(target, |u| {
+                upper_crossing(previous.as_ref(), t_previous, t, 0.0, u)
+            });
+            efficacy.push(bound);
+            cumulative_alpha.push(sides * total);
+            spent = total;
+            let lower = if two_sided { -bound } else { -TAIL };
+            previous = Some(Stage::advance(
+                previous.as_ref(),
+                t_previous,
+                t,
+                0.0,
+                lower,
+                bound,
+            ));
+            t_previous = t;
+        }
+
+        let beta = 1.0 - options.power;
+        let fixed_drift =
+            normal::quantile(1.0 - options.alpha / sides) + normal::quantile(options.power);
+        let (drift, futility) = match options.futility {
+            None => {
+                let drift = solve_increasing(options.power, |delta| {
+                    power(information, &efficacy, None, two_sided, delta)
+                });
+                (drift, None)
+            }
+            Some(spending) => {
+                // The drift at which the futility boundary meets the
+                // efficacy boundary at the final look.
+                let drift = solve_increasing(0.0, |delta| {
+                    let futility = futility_bounds(information, &efficacy, spending, beta, delta);
+                    futility[futility.len() - 1] - efficacy[efficacy.len() - 1]
+                });
+                let mut futility = futility_bounds(information, &efficacy, spending, beta, drift);
+                let last = futility.len() - 1;
+                futility[last] = efficacy[last];
+                (drift, Some(futility))
+            }
+        };
+
+        Ok(GroupSequentialDesign {
+            information: information.to_vec(),
+            efficacy,
+            futility,
+            cumulative_alpha,
+            drift,
+            inflation_factor: (drift / fixed_drift).powi(2),
+            alternative: options.alternative,
+        })
+    }
+
+    /// The decision at look `look` (starting at 0) for a z statistic `z`,
+    /// signed like the difference `p2 - p1`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `look` is not a look of the design.
+    pub fn evaluate(&self, look: usize, z: f64) -> Decision {
+        let bound = self.efficacy[look];
+        let directed = match self.alternative {
+            Alternative::Less => -z,
+            Alternative::Greater => z,
+            Alternative::TwoSided => z.abs(),
+        };
+        if directed >= bound {
+            return Decision::Efficacy(if z > 0.0 { Variant::B } else { Variant::A });
+        }
+        let last = look + 1 == self.efficacy.len();
+        let futile = match &self.futility {
+            Some(futility) => directed <= futility[look],
+            None => false,
+        };
+        if last || futile {
+            Decision::Futility
+        } else {
+            Decision::Continue
+        }
+    }
+
+    /// Runs the conversion test on the cumulative counts at look `look` and
+    /// compares its z statistic with the boundaries.
+    ///
+    /// `options` configures the test itself, in particular the sample ratio
+    /// mismatch check against `options.allocation`. The decision depends only
+    /// on the z statistic, so `options.alpha` and `options.alternative` set
+    /// the p-value and interval reported in [`InterimResult::test`] but not
+    /// the stopping rule.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`ab_conversion_test_counts`], and
+    /// [`Error::InvalidParameter`] if `look` is not a look of the design.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use statistical_computing::conversion::{Arm, TestOptions};
+    /// use statistical_computing::group_sequential::{Decision, DesignOptions, GroupSequentialDesign};
+    ///
+    /// let design = GroupSequentialDesign::new(&[0.5, 1.0], &DesignOptions::default()).unwrap();
+    /// // Two treatment units for every control unit.
+    /// let options = TestOptions::default().allocation(2.0);
+    /// let look = design
+    ///     .evaluate_counts(0, Arm::new(100, 1000), Arm::new(230, 2000), &options)
+    ///     .unwrap();
+    /// assert!(!look.test.srm.unwrap().mismatch);
+    /// assert_eq!(look.decision, Decision::Continue);
+    /// ```
+    pub fn evaluate_counts(
+        &self,
+        look: usize,
+        a: Arm,
+        b: Arm,
+        options: &TestOptions,
+    ) -> Result<InterimResult> {
+        if look >= self.efficacy.len() {
+            return Err(Error::InvalidParameter {
+                name: "look",
+                value: look as f64,
+            });
+        }
+        let test = ab_conversion_test_counts(a, b, options)?;
+        Ok(InterimResult {
+            look,
+            decision: self.evaluate(look, test.z),
+            test,
+        })
+    }
+}
+
+/// The sub-density of the z statistic at one look over the region where
+/// the experiment continues, as Simpson-weighted values on a grid.
+struct Stage {
+    points: Vec<f64>,
+    /// The density at each point multiplied by its Simpson weight.
+    weighted: Vec<f64>,
+}
+
+impl Stage {
+    /// The sub-density at information `t` over `[lower, upper]`, continuing
+    /// from `previous` at information `t_previous`, with drift `delta`.
+    fn advance(
+        previous: Option<&Stage>,
+        t_previous: f64,
+        t: f64,
+        delta: f64,
+        lower: f64,
+        upper: f64,
+    ) -> Stage {
+        let mean = delta * t.sqrt();
+        let lower = lower.max(mean - TAIL);
+        let upper = upper.min(mean + TAIL).max(lower);
+        let intervals = (((upper - lower) / GRID_STEP).ceil() as usize).max(2);
+        let intervals = intervals + intervals % 2;
+        let h = (upper - lower) / intervals as f64;
+        let points: Vec<f64> = (0..=intervals).map(|i| lower + i as f64 * h).collect();
+        let weighted = points
+            .iter()
+            .enumerate()
+            .map(|(i, &z)| {
+                let weight = if i == 0 || i == intervals {
+                    h / 3.0
+                } else if i % 2 == 1 {
+                    4.0 * h / 3.0
+                } else {
+                    2.0 * h / 3.0
+                };
+                let density = match previous {
+                    None => normal::pdf(z - mean),
+                    Some(stage) => {
+                        let increment = t - t_previous;
+                        let scale = (t / increment).sqrt();
+                        stage
+                            .points
+                            .iter()
+                            .zip(&stage.weighted)
+                            .map(|(&y, &w)| {
+                                let x = (z * t.sqrt() - y * t_previous.sqrt() - delta * increment)
+                                    / increment.sqrt();
+                                w * scale * normal::pdf(x)
+                            })
+                            .sum()
+                    }
+                };
+                weight * density
+            })
+            .collect();
+        Stage { points, weighted }
+    }
+}
+
+/// The probability of continuing to information `t` and then reaching `u`
+/// or above.
+fn upper_crossing(previous: Option<&Stage>, t_previous: f64, t: f64, delta: f64, u: f64) -> f64 {
+    match previous {
+        None => normal::sf(u - delta * t.sqrt()),
+        Some(stage) => {
+            let increment = t - t_previous;
+            stage
+                .points
+                .iter()
+                .zip(&stage.weighted)
+                .map(|(&y, &w)| {
+                    w * normal::sf(
+                        (u * t.sqrt() - y * t_previous.sqrt() - delta * increment)
+                            / increment.sqrt(),
+                    )
+                })
+                .sum()
+        }
+    }
+}
+
+/// The probability of continuing to information `t` and then reaching `l`
+/// or below.
+fn lower_crossing(previous: Option<&Stage>, t_previous: f64, t: f64, delta: f64, l: f64) -> f64 {
+    match previous {
+        None => normal::cdf(l - delta * t.sqrt()),
+        Some(stage) => {
+            let increment = t - t_previous;
+            stage
+                .points
+                .iter()
+                .zip(&stage.weighted)
+                .map(|(&y, &w)| {
+                    w * normal::cdf(
+                        (l * t.sqrt() - y * t_previous.sqrt() - delta * increment)
+                            / increment.sqrt(),
+                    )
+                })
+                .sum()
+        }
+    }
+}
+
+/// The probability of crossing an efficacy boundary at some look, with drift
+/// `delta` and optional futility boundaries.
+fn power(
+    information: &[f64],
+    efficacy: &[f64],
+    futility: Option<&[f64]>,
+    two_sided: bool,
+    delta: f64,
+) -> f64 {
+    let mut previous: Option<Stage> = None;
+    let mut t_previous = 0.0;
+    let mut total = 0.0;
+    for (k, &t) in information.iter().enumerate() {
+        total += upper_crossing(previous.as_ref(), t_previous, t, delta, efficacy[k]);
+        let lower = match futility {
+            Some(futility) => futility[k],
+            None if two_sided => -efficacy[k],
+            None => f64::NEG_INFINITY,
+        };
+        previous = Some(Stage::advance(
+            previous.as_ref(),
+            t_previous,
+            t,
+            delta,
+            lower,
+            efficacy[k],
+        ));
+        t_previous = t;
+    }
+    total
+}
+
+/// Futility boundaries spending `beta` under drift `delta`, with the
+/// efficacy boundaries fixed.
+fn futility_bounds(
+    information: &[f64],
+    efficacy: &[f64],
+    spending: Spending,
+    beta: f64,
+    delta: f64,
+) -> Vec<f64> {
+    let mut futility = Vec::with_capacity(information.len());
+    let mut previous: Option<Stage> = None;
+    let mut t_previous = 0.0;
+    let mut spent = 0.0;
+    for (k, &t) in information.iter().enumerate() {
+        let total = spending.spent(beta, t);
+        let target = total - spent;
+        spent = total;
+        let bound = -solve_decreasing(target, |l| {
+            lower_crossing(previous.as_ref(), t_previous, t, delta, -l)
+        });
+        let bound = if k + 1 < information.len() {
+            bound.min(efficacy[k])
+        } else {
+            bound
+        };
+        futility.push(bound);
+        previous = Some(Stage::advance(
+            previous.as_ref(),
+            t_previous,
+            t,
+            delta,
+            bound,
+            efficacy[k],
+        ));
+        t_previous = t;
+    }
+    futility
+}
+
+/// Solves `f(x) = target` by bisection for `f` decreasing in `x`.
+fn solve_decreasing(target: f64, f: impl Fn(f64) -> f64) -> f64 {
+    let (mut low, mut high) = (-20.0, 40.0);
+    if target <= 0.0 {
+        return high;
+    }
+    for _ in 0..60 {
+        let mid = 0.5 * (low + high);
+        if f(mid) > target {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    0.5 * (low + high)
+}
+
+/// Solves `f(x) = target` by bisection for `f` increasing in `x >= 0`.
+fn solve_increasing(target: f64, f: impl Fn(f64) -> f64) -> f64 {
+    let (mut low, mut high) = (0.0, 20.0);
+    for _ in 0..40 {
+        let mid = 0.5 * (low + high);
+        if f(mid) < target {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    0.5 * (low + high)
+}
+
+fn validate(information: &[f64], options: &DesignOptions) -> Result<()> {
+    for (name, value) in [("alpha", options.alpha), ("power", options.power)] {
+        if !(value > 0.0 && value < 1.0) {
+            return Err(Error::InvalidParameter { name, value });
+        }
+    }
+    if options.futility.is_some() && options.alternative == Alternative::TwoSided {
+        return Err(Error::InvalidParameter {
+            name: "futility",
+            value: f64::NAN,
+        });
+    }
+    options.efficacy.validate()?;
+    if let Some(futility) = options.futility {
+        futility.validate()?;
+    }
+    let mut previous = 0.0;
+    for &t in information {
+        if !(t > previous && t <= 1.0) {
+            return Err(Error::InvalidParameter {
+                name: "information",
+                value: t,
+            });
+        }
+        previous = t;
+    }
+    if previous != 1.0 {
+        return Err(Error::InvalidParameter {
+            name: "information",
+            value: previous,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! Confidence intervals for proportions and for their differences, ratios
+//! and odds ratios.
+//!
+//! Intervals here are computed for a given standard normal critical value
+//! `z`, so the same routine serves two-sided intervals (with the
+//! `(1 + confidence) / 2` quantile) and one-sided bounds (with the
+//! `confidence` quantile).
+
+/// How the confidence interval for a difference of proportions is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum IntervalMethod {
+    /// The unpooled Wald interval, `d ± z·se`. Simple, but it undercovers
+    /// when rates are near 0 or 1 or samples are small.
+    #[default]
+    Wald,
+    /// Newcombe's hybrid score interval, built from the Wilson intervals of
+    /// each rate.
+    Newcombe,
+    /// The Agresti-Caffo interval: the Wald interval after adding one
+    /// success and one failure to each group.
+    AgrestiCaffo,
+    /// The Miettinen-Nurminen score interval, which inverts the score test
+    /// using the maximum likelihood rates restricted to each candidate
+    /// difference.
+    MiettinenNurminen,
+}
+
+/// How the confidence interval for a ratio of proportions is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RatioMethod {
+    /// Katz's interval, the Wald interval of the log ratio from the delta
+    /// method, transformed back.
+    #[default]
+    Log,
+    /// Fieller's interval, the set of ratios `r` for which `p2 - r·p1` is
+    /// not significantly different from zero. It is unbounded when the
+    /// baseline rate is not significantly above zero.
+    Fieller,
+}
+
+/// How the confidence interval for an odds ratio is built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum OddsRatioMethod {
+    /// Woolf's interval, the Wald interval of the log odds ratio.
+    #[default]
+    Woolf,
+    /// The exact conditional interval that inverts Fisher's test, as
+    /// reported by [`fisher_exact`]. Rates are rounded to whole counts.
+    ///
+    /// [`fisher_exact`]: crate::exact::fisher_exact
+    Exact,
+}
+
+/// The Wilson score interval for a single proportion `p` observed in `n`
+/// trials.
+pub fn wilson(p: f64, n: f64, z: f64) -> (f64, f64) {
+    let z2 = z * z;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    ((center - half).max(0.0), (center + half).min(1.0))
+}
+
+/// An interval for the difference `p2 - p1` of two proportions.
+///
+/// # Arguments
+///
+/// * `method` - How to build the interval.
+/// * `p1` - The rate observed in the first group.
+/// * `n1` - The size of the first group.
+/// * `p2` - The rate observed in the second group.
+/// * `n2` - The size of the second group.
+/// * `z` - The standard normal critical value of each bound.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::interval::{difference, IntervalMethod};
+///
+/// // Newcombe (1998), example (a): 56/70 against 48/80.
+/// let (lo, hi) = difference(IntervalMethod::Newcombe, 48. / 80., 80., 56. / 70., 70., 1.959964);
+/// assert!((lo - 0.0524).abs() < 1e-4 && (hi - 0.3339).abs() < 1e-4);
+/// ```
+pub fn difference(
+    method: IntervalMethod,
+    p1: f64,
+    n1: f64,
+    p2: f64,
+    n2: f64,
+    z: f64,
+) -> (f64, f64) {
+    let d = p2 - p1;
+    let (lo, hi) = match method {
+        IntervalMethod::Wald => {
+            let se = (p1 * (1.0 - p1) / n1 + p2 * (1.0 - p2) / n2).sqrt();
+            (d - z * se, d + z * se)
+        }
+        IntervalMethod::Newcombe => {
+            let (l1, u1) = wilson(p1, n1, z);
+            let (l2, u2) = wilson(p2, n2, z);
+            (
+                d - ((p2 - l2).powi(2) + (u1 - p1).powi(2)).sqrt(),
+                d + ((u2 - p2).powi(2) + (p1 - l1).powi(2)).sqrt(),
+            )
+        }
+        IntervalMethod::AgrestiCaffo => {
+            let (t1, m1) = ((p1 * n1 + 1.0) / (n1 + 2.0), n1 + 2.0);
+            let (t2, m2) = ((p2 * n2 + 1.0) / (n2 + 2.0), n2 + 2.0);
+            let se = (t1 * (1.0 - t1) / m1 + t2 * (1.0 - t2) / m2).sqrt();
+            (t2 - t1 - z * se, t2 - t1 + z * se)
+        }
+        IntervalMethod::MiettinenNurminen => miettinen_nurminen(p1, n1, p2, n2, z),
+    };
+    (lo.max(-1.0), hi.min(1.0))
+}
+
+/// An interval for the ratio `p2 / p1` of two proportions.
+///
+/// Takes the same arguments as [`difference`]. The interval is `(0, ∞)`
+/// when a rate is zero.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::interval::{ratio, RatioMethod};
+///
+/// // 30 of 200 against 20 of 200, a ratio of 1.5.
+/// let (lo, hi) = ratio(RatioMethod::Log, 0.1, 200., 0.15, 200., 1.959964);
+/// assert!((lo - 0.8822).abs() < 1e-4 && (hi - 2.5503).abs() < 1e-4);
+/// let (lo, hi) = ratio(RatioMethod::Fieller, 0.1, 200., 0.15, 200., 1.959964);
+/// assert!((lo - 0.8837).abs() < 1e-4 && (hi - 2.7433).abs() < 1e-4);
+/// ```
+pub fn ratio(method: RatioMethod, p1: f64, n1: f64, p2: f64, n2: f64, z: f64) -> (f64, f64) {
+    if p1 <= 0.0 || p2 <= 0.0 {
+        return (0.0, f64::INFINITY);
+    }
+    let r = p2 / p1;
+    match method {
+        RatioMethod::Log => {
+            let se = ((1.0 - p1) / (n1 * p1) + (1.0 - p2) / (n2 * p2)).sqrt();
+            (r * (-z * se).exp(), r * (z * se).exp())
+        }
+        RatioMethod::Fieller => {
+            let (v1, v2) = (p1 * (1.0 - p1) / n1, p2 * (1.0 - p2) / n2);
+            // The bounds solve (p2 - r p1)^2 = z^2 (v2 + r^2 v1).
+            let a = p1 * p1 - z * z * v1;
+            if a <= 0.0 {
+                return (0.0, f64::INFINITY);
+            }
+            let half = (p1 * p1 * p2 * p2 - a * (p2 * p2 - z * z * v2))
+                .max(0.0)
+                .sqrt();
+            (((p1 * p2 - half) / a).max(0.0), (p1 * p2 + half) / a)
+        }
+    }
+}
+
+/// Woolf's interval for the odds ratio of the second group against the
+/// first.
+///
+/// Takes the same arguments as [`difference`] and returns the estimate
+/// followed by the bounds. When a cell of the 2x2 table is empty, half a
+/// unit is added to every cell, the Haldane-Anscombe correction.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::interval::woolf;
+///
+/// let (odds_ratio, lo, hi) = woolf(0.1, 200., 0.15, 200., 1.959964);
+/// assert!((odds_ratio - 1.5882).abs() < 1e-4);
+/// assert!((lo - 0.8687).abs() < 1e-4 && (hi - 2.9037).abs() < 1e-4);
+/// ```
+pub fn woolf(p1: f64, n1: f64, p2: f64, n2: f64, z: f64) -> (f64, f64, f64) {
+    let mut cells = [p2 * n2, (1.0 - p2) * n2, p1 * n1, (1.0 - p1) * n1];
+    if cells.iter().any(|&c| c <= 0.0) {
+        for c in &mut cells {
+            *c += 0.5;
+        }
+    }
+    let [a, b, c, d] = cells;
+    let odds_ratio = a * d / (b * c);
+    let se = cells.iter().map(|c| 1.0 / c).sum::<f64>().sqrt();
+    (
+        odds_ratio,
+        odds_ratio * (-z * se).exp(),
+        odds_ratio * (z * se).exp(),
+    )
+}
+
+/// Inverts the Miettinen-Nurminen score statistic by bisection on each side
+/// of the observed difference.
+fn miettinen_nurminen(p1: f64, n1: f64, p2: f64, n2: f64, z: f64) -> (f64, f64) {
+    let d = p2 - p1;
+    let score = |delta: f64| {
+        let (r2, r1) = restricted_rates(p2, n2, p1, n1, delta);
+        let total = n1 + n2;
+        let variance = (r1 * (1.0 - r1) / n1 + r2 * (1.0 - r2) / n2) * total / (total - 1.0);
+        let diff = d - delta;
+        if variance > 0.0 {
+            diff / variance.sqrt()
+        } else if diff == 0.0 {
+            0.0
+        } else {
+            diff.signum() * f64::INFINITY
+        }
+    };
+    // The score falls monotonically as the candidate difference grows.
+    let solve = |mut below: f64, mut above: f64, target: f64| {
+        for _ in 0..100 {
+            let mid = 0.5 * (below + above);
+            if score(mid) > target {
+                below = mid;
+            } else {
+                above = mid;
+            }
+        }
+        0.5 * (below + above)
+    };
+    let lo = if d <= -1.0 { -1.0 } else { solve(-1.0, d, z) };
+    let hi = if d >= 1.0 { 1.0 } else { solve(d, 1.0, -z) };
+    (lo, hi)
+}
+
+/// The maximum likelihood rates of two groups under the constraint
+/// `rate_a - rate_b = delta`, following Farrington & Manning (1990).
+pub(crate) fn restricted_rates(pa: f64, na: f64, pb: f64, nb: f64, delta: f64) -> (f64, f64) {
+    let theta = nb / na;
+    let a = 1.0 + theta;
+    let b = -(1.0 + theta + pa + theta * pb + delta * (theta + 2.0));
+    let c = delta * delta + delta * (2.0 * pa + theta + 1.0) + pa + theta * pb;
+    let d = -pa * delta * (1.0 + delta);
+    let v = b.powi(3) / (27.0 * a.powi(3)) - b * c / (6.0 * a * a) + d / (2.0 * a);
+    let u_squared = b * b / (9.0 * a * a) - c / (3.0 * a);
+    let u = if v < 0.0 { -1.0 } else { 1.0 } * u_squared.max(0.0).sqrt();
+    let ratio = if u == 0.0 {
+        0.0
+    } else {
+        (v / u.powi(3)).clamp(-1.0, 1.0)
+    };
+    let w = (std::f64::consts::PI + ratio.acos()) / 3.0;
+    let ra = (2.0 * u * w.cos() - b / (3.0 * a)).clamp(0.0, 1.0);
+    let rb = (ra - delta).clamp(0.0, 1.0);
+    (ra, rb)
+}
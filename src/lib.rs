@@ -0,0 +1,67 @@
+//! A library for statistical computing in Rust.
+//!
+//! The crate is organised around the questions that come up when analysing
+//! online experiments:
+//!
+//! * [`conversion`] compares the conversion rates of two variants with the
+//!   two-proportion z-test, and [`interval`] builds the confidence intervals
+//!   it reports.
+//! * [`two_sample`] compares the means of continuous metrics such as
+//!   revenue with Student's and Welch's t-tests, and [`rank`] offers
+//!   rank-based tests for skewed metrics.
+//! * [`cuped`] reduces the variance of either kind of test with
+//!   pre-experiment covariates.
+//! * [`stratified`] combines the comparison across segments such as
+//!   platforms or countries with the Mantel-Haenszel methods.
+//! * [`ratio`] handles ratio metrics such as clicks per session with the
+//!   delta method.
+//! * [`bootstrap`] builds resampling intervals for arbitrary statistics,
+//!   drawing from the seedable generator in [`random`].
+//! * [`exact`] holds exact counterparts for experiments too small for the
+//!   normal approximation.
+//! * [`bayesian`] answers the same question with posterior probabilities
+//!   and expected losses instead of a significance test.
+//! * [`sequential`] monitors a running experiment with an always-valid
+//!   p-value, so it can be checked at any time, and [`group_sequential`]
+//!   plans a fixed number of interim looks with alpha-spending boundaries.
+//! * [`power`] plans an experiment: the sample size or duration needed to
+//!   detect an effect, and the smallest effect a given sample can detect.
+//! * [`srm`] checks that traffic was split as intended before a winner is
+//!   trusted; the conversion tests run it automatically.
+//! * [`multi_arm`] extends the comparison to more than two variants, and
+//!   [`correction`] adjusts for the multiple comparisons that follow.
+//! * [`csv`] reads raw event logs into the per-variant counts and revenue
+//!   summaries the tests take.
+//! * [`distributions`] and [`special`] provide the probability functions the
+//!   tests are built on.
+//!
+//! Invalid input is reported through the shared [`Error`] type.
+
+pub mod bayesian;
+pub mod bootstrap;
+pub mod conversion;
+pub mod correction;
+pub mod csv;
+pub mod cuped;
+pub mod distributions;
+pub mod error;
+pub mod exact;
+pub mod group_sequential;
+pub mod interval;
+pub mod multi_arm;
+pub mod power;
+pub mod random;
+pub mod rank;
+pub mod ratio;
+pub mod sequential;
+pub mod special;
+pub mod srm;
+pub mod stratified;
+pub mod two_sample;
+
+pub use conversion::{
+    ab_conversion_test, ab_conversion_test_counts, ab_conversion_test_with, Alternative, Arm,
+    ConversionTestResult, TestOptions, Variant,
+};
+pub use error::{Error, Result};
+pub use interval::{IntervalMethod, OddsRatioMethod, RatioMethod};
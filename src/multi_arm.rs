@@ -0,0 +1,185 @@
+//! A/B/n tests comparing the conversion rates of more than two variants.
+//!
+//! The analysis has two stages. An omnibus test of independence on the 2 x k
+//! table of conversions asks whether any arm differs at all; pairwise
+//! two-proportion tests against the control then say which arms differ and
+//! by how much.
+
+use crate::conversion::{ab_conversion_test_counts, Arm, ConversionTestResult, TestOptions};
+use crate::correction::{adjust, AdjustedPValues, Correction};
+use crate::distributions::chi_squared;
+use crate::error::{Error, Result};
+use crate::srm::{self, SrmResult};
+
+/// The result of an omnibus test of independence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OmnibusTest {
+    /// The test statistic.
+    pub statistic: f64,
+    /// The degrees of freedom of its chi-squared reference distribution.
+    pub df: f64,
+    /// The p-value of the statistic.
+    pub p_value: f64,
+}
+
+/// The outcome of [`multi_arm_test`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiArmResult {
+    /// Pearson's chi-squared test of independence.
+    pub chi_square: OmnibusTest,
+    /// The likelihood-ratio G-test of independence.
+    pub g_test: OmnibusTest,
+    /// Whether the chi-squared test rejects independence at `options.alpha`.
+    pub significant: bool,
+    /// The sample ratio mismatch check over all arms, expecting each
+    /// treatment to get `options.allocation` units per control unit.
+    pub srm: Option<SrmResult>,
+    /// The test of each treatment arm against the control, in the order the
+    /// arms were given, or the error that kept a pair from being tested, such
+    /// as an arm too small for the normal approximation. In each result the
+    /// control is [`Variant::A`] and the treatment is [`Variant::B`].
+    ///
+    /// [`Variant::A`]: crate::conversion::Variant::A
+    /// [`Variant::B`]: crate::conversion::Variant::B
+    pub pairwise: Vec<Result<ConversionTestResult>>,
+}
+
+impl MultiArmResult {
+    /// Adjusts the p-values of the pairwise comparisons for multiplicity.
+    ///
+    /// A pair that could not be tested counts as a comparison with a p-value
+    /// of 1, so it never rejects but still takes its share of `alpha`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if `alpha` is not strictly between
+    /// 0 and 1.
+    pub fn adjusted(&self, method: Correction, alpha: f64) -> Result<AdjustedPValues> {
+        let p_values: Vec<f64> = self
+            .pairwise
+            .iter()
+            .map(|r| r.as_ref().map_or(1.0, |r| r.p_value))
+            .collect();
+        adjust(&p_values, method, alpha)
+    }
+}
+
+/// Tests several variants for a difference in conversion rates.
+///
+/// The first arm is the control. The pairwise comparisons are each run at
+/// `options.alpha` with no adjustment for multiplicity; see
+/// [`MultiArmResult::adjusted`] or [`dunnett`] to correct them. A pair that
+/// cannot be tested keeps its error in [`MultiArmResult::pairwise`] without
+/// failing the others or the omnibus test.
+///
+/// [`dunnett`]: crate::correction::dunnett
+///
+/// # Errors
+///
+/// Returns [`Error::TooFewGroups`] for fewer than two arms,
+/// [`Error::ZeroVariance`] if no arm converted or every unit did,
+/// [`Error::SampleRatioMismatch`] if the options block results whose arm
+/// sizes fail the sample ratio mismatch check, [`Error::Overflow`] if the
+/// arm sizes overflow when combined, and the errors of [`Arm::validate`].
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::{Arm, TestOptions};
+/// use statistical_computing::multi_arm::multi_arm_test;
+///
+/// let arms = [Arm::new(100, 1000), Arm::new(105, 1000), Arm::new(150, 1000)];
+/// let result = multi_arm_test(&arms, &TestOptions::default()).unwrap();
+/// assert!(result.significant);
+/// assert!(result.pairwise[0].as_ref().unwrap().winner.is_none());
+/// assert!(result.pairwise[1].as_ref().unwrap().winner.is_some());
+/// ```
+///
+/// A pair that cannot be tested does not hide the rest of the result:
+///
+/// ```
+/// use statistical_computing::conversion::{Arm, TestOptions};
+/// use statistical_computing::multi_arm::multi_arm_test;
+/// use statistical_computing::Error;
+///
+/// // Neither the control nor the first treatment converted.
+/// let arms = [Arm::new(0, 1000), Arm::new(0, 1000), Arm::new(30, 1000)];
+/// let result = multi_arm_test(&arms, &TestOptions::default()).unwrap();
+/// assert!(result.significant);
+/// assert_eq!(result.pairwise[0], Err(Error::ZeroVariance));
+/// assert!(result.pairwise[1].as_ref().unwrap().winner.is_some());
+///
+/// // The first treatment has too few units for the z-test.
+/// let arms = [Arm::new(10, 1000), Arm::new(3, 4), Arm::new(30, 1000)];
+/// let result = multi_arm_test(&arms, &TestOptions::default()).unwrap();
+/// assert!(result.significant);
+/// assert!(matches!(result.pairwise[0], Err(Error::InsufficientSample { .. })));
+/// assert!(result.pairwise[1].is_ok());
+/// ```
+pub fn multi_arm_test(arms: &[Arm], options: &TestOptions) -> Result<MultiArmResult> {
+    options.validate()?;
+    if arms.len() < 2 {
+        return Err(Error::TooFewGroups {
+            required: 2,
+            actual: arms.len(),
+        });
+    }
+    let mut successes: u64 = 0;
+    let mut trials: u64 = 0;
+    for arm in arms {
+        arm.validate()?;
+        successes = successes
+            .checked_add(arm.successes)
+            .ok_or(Error::Overflow)?;
+        trials = trials.checked_add(arm.trials).ok_or(Error::Overflow)?;
+    }
+    if successes == 0 || successes == trials {
+        return Err(Error::ZeroVariance);
+    }
+    let sizes: Vec<u64> = arms.iter().map(|arm| arm.trials).collect();
+    let srm = srm::check(&sizes, options)?;
+
+    let pooled = successes as f64 / trials as f64;
+    let mut chi_square = 0.0;
+    let mut g = 0.0;
+    for arm in arms {
+        let observed = [arm.successes as f64, (arm.trials - arm.successes) as f64];
+        let expected = [
+            arm.trials as f64 * pooled,
+            arm.trials as f64 * (1.0 - pooled),
+        ];
+        for (o, e) in observed.iter().zip(expected) {
+            if e > 0.0 {
+                chi_square += (o - e).powi(2) / e;
+            }
+            if *o > 0.0 {
+                g += 2.0 * o * (o / e).ln();
+            }
+        }
+    }
+    let df = (arms.len() - 1) as f64;
+    let chi_square = OmnibusTest {
+        statistic: chi_square,
+        df,
+        p_value: chi_squared::sf(chi_square, df),
+    };
+    let g_test = OmnibusTest {
+        statistic: g,
+        df,
+        p_value: chi_squared::sf(g, df),
+    };
+
+    let control = arms[0];
+    let pairwise = arms[1..]
+        .iter()
+        .map(|&arm| ab_conversion_test_counts(control, arm, options))
+        .collect();
+
+    Ok(MultiArmResult {
+        significant: chi_square.p_value < options.alpha,
+        srm,
+        chi_square,
+        g_test,
+        pairwise,
+    })
+}
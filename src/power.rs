@@ -0,0 +1,501 @@
+//! Sample sizes and power for planning a conversion experiment.
+//!
+//! [`sample_size`] and [`power`] answer the question for a fixed effect or a
+//! fixed sample, [`minimum_detectable_effect`] finds the smallest effect a
+//! sample can detect, and [`Planner`] turns daily traffic into durations.
+//!
+//! The calculations match the test in [`ab_conversion_test`]: the statistic
+//! is standardised with the pooled rate under the null hypothesis and
+//! distributed around the true difference with the unpooled variance under
+//! the alternative.
+//!
+//! [`ab_conversion_test`]: crate::conversion::ab_conversion_test
+
+use crate::conversion::{Alternative, MIN_SAMPLE_SIZE};
+use crate::distributions::normal;
+use crate::error::{Error, Result};
+
+/// The smallest effect an experiment should be able to detect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    /// A difference in conversion rates, such as `0.01` for one percentage
+    /// point.
+    Absolute(f64),
+    /// A lift relative to the baseline, such as `0.05` for 5%.
+    Relative(f64),
+}
+
+impl Effect {
+    /// The conversion rate of the treatment when the effect is applied to
+    /// `baseline`.
+    pub fn treatment_rate(self, baseline: f64) -> f64 {
+        match self {
+            Effect::Absolute(d) => baseline + d,
+            Effect::Relative(r) => baseline * (1.0 + r),
+        }
+    }
+}
+
+/// Settings for [`sample_size`] and [`power`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerOptions {
+    /// The significance level of the planned test.
+    pub alpha: f64,
+    /// The probability of detecting the effect if it is real.
+    pub power: f64,
+    /// The alternative hypothesis of the planned test.
+    pub alternative: Alternative,
+    /// The number of treatment units per control unit.
+    pub ratio: f64,
+}
+
+impl Default for PowerOptions {
+    fn default() -> Self {
+        PowerOptions {
+            alpha: 0.05,
+            power: 0.8,
+            alternative: Alternative::TwoSided,
+            ratio: 1.0,
+        }
+    }
+}
+
+impl PowerOptions {
+    /// Sets the significance level.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the desired power.
+    pub fn power(mut self, power: f64) -> Self {
+        self.power = power;
+        self
+    }
+
+    /// Sets the alternative hypothesis.
+    pub fn alternative(mut self, alternative: Alternative) -> Self {
+        self.alternative = alternative;
+        self
+    }
+
+    /// Sets the number of treatment units per control unit.
+    pub fn ratio(mut self, ratio: f64) -> Self {
+        self.ratio = ratio;
+        self
+    }
+
+    /// Checks that `alpha` and the power lie in `(0, 1)` and the allocation
+    /// ratio is positive.
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [("alpha", self.alpha), ("power", self.power)] {
+            if !(value > 0.0 && value < 1.0) {
+                return Err(Error::InvalidParameter { name, value });
+            }
+        }
+        if !(self.ratio > 0.0 && self.ratio.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "ratio",
+                value: self.ratio,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The number of units in each arm of an experiment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SampleSize {
+    /// The units in the control, version A.
+    pub a: u64,
+    /// The units in the treatment, version B.
+    pub b: u64,
+}
+
+impl SampleSize {
+    /// The units across both arms.
+    pub fn total(&self) -> u64 {
+        self.a.saturating_add(self.b)
+    }
+}
+
+/// The sample size needed to detect `effect` over a `baseline` conversion
+/// rate.
+///
+/// The control size is rounded up and the treatment size is the control
+/// size times `options.ratio`, also rounded up.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidProportion`] if the baseline or treatment rate is
+/// outside `(0, 1)`, and [`Error::InvalidParameter`] for invalid options, a
+/// zero effect, or an effect in the opposite direction to a one-sided
+/// alternative.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::power::{sample_size, Effect, PowerOptions};
+///
+/// let size = sample_size(0.10, Effect::Relative(0.10), &PowerOptions::default()).unwrap();
+/// assert_eq!(size.a, 14751);
+/// assert_eq!(size.b, 14751);
+/// ```
+pub fn sample_size(baseline: f64, effect: Effect, options: &PowerOptions) -> Result<SampleSize> {
+    options.validate()?;
+    let plan = Plan::new(baseline, effect, options)?;
+    let z_alpha = options.alternative.critical_value(options.alpha);
+    let z_beta = normal::quantile(options.power);
+    let root = (z_alpha * plan.null_sd + z_beta * plan.alternative_sd) / plan.difference.abs();
+    let a = (root * root).ceil();
+    let b = (a * options.ratio).ceil();
+    if b >= u64::MAX as f64 {
+        return Err(Error::Overflow);
+    }
+    Ok(SampleSize {
+        a: (a as u64).max(MIN_SAMPLE_SIZE),
+        b: (b as u64).max(MIN_SAMPLE_SIZE),
+    })
+}
+
+/// The probability that a test on an experiment of the given size detects
+/// `effect` over a `baseline` conversion rate.
+///
+/// The allocation ratio is taken from `size`; `options.power` and
+/// `options.ratio` are not used.
+///
+/// # Errors
+///
+/// Returns the errors of [`sample_size`], and [`Error::InsufficientSample`]
+/// if an arm is smaller than [`MIN_SAMPLE_SIZE`].
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::power::{power, Effect, PowerOptions, SampleSize};
+///
+/// let size = SampleSize { a: 14751, b: 14751 };
+/// let power = power(0.10, Effect::Relative(0.10), size, &PowerOptions::default()).unwrap();
+/// assert!((power - 0.8).abs() < 0.001);
+/// ```
+pub fn power(
+    baseline: f64,
+    effect: Effect,
+    size: SampleSize,
+    options: &PowerOptions,
+) -> Result<f64> {
+    let smallest = size.a.min(size.b);
+    if smallest < MIN_SAMPLE_SIZE {
+        return Err(Error::InsufficientSample {
+            required: MIN_SAMPLE_SIZE,
+            actual: smallest,
+        });
+    }
+    let options = options.ratio(size.b as f64 / size.a as f64);
+    options.validate()?;
+    let plan = Plan::new(baseline, effect, &options)?;
+    let z_alpha = options.alternative.critical_value(options.alpha);
+    let n = (size.a as f64).sqrt();
+    let detect = |d: f64| normal::cdf((d * n - z_alpha * plan.null_sd) / plan.alternative_sd);
+    Ok(match options.alternative {
+        Alternative::Greater => detect(plan.difference),
+        Alternative::Less => detect(-plan.difference),
+        Alternative::TwoSided => detect(plan.difference) + detect(-plan.difference),
+    })
+}
+
+/// The smallest absolute difference in rates that an experiment of the
+/// given size detects with probability `options.power`.
+///
+/// The effect is an increase over `baseline` unless the alternative is
+/// [`Alternative::Less`], in which case it is a decrease. Returns `None` if
+/// no possible effect reaches the power.
+///
+/// # Errors
+///
+/// Returns the errors of [`power`].
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::power::{minimum_detectable_effect, PowerOptions, SampleSize};
+///
+/// let size = SampleSize { a: 14751, b: 14751 };
+/// let mde = minimum_detectable_effect(0.10, size, &PowerOptions::default()).unwrap();
+/// assert!((mde.unwrap() - 0.01).abs() < 1e-5);
+/// ```
+pub fn minimum_detectable_effect(
+    baseline: f64,
+    size: SampleSize,
+    options: &PowerOptions,
+) -> Result<Option<f64>> {
+    if !(baseline > 0.0 && baseline < 1.0) {
+        return Err(Error::InvalidProportion(baseline));
+    }
+    let (sign, limit) = match options.alternative {
+        Alternative::Less => (-1.0, baseline),
+        Alternative::Greater | Alternative::TwoSided => (1.0, 1.0 - baseline),
+    };
+    let reaches = |m: f64| -> Result<bool> {
+        Ok(power(baseline, Effect::Absolute(sign * m), size, options)? >= options.power)
+    };
+    let (mut low, mut high) = (0.0, limit * (1.0 - 1e-9));
+    if !reaches(high)? {
+        return Ok(None);
+    }
+    for _ in 0..60 {
+        let mid = 0.5 * (low + high);
+        if reaches(mid)? {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    Ok(Some(sign * high))
+}
+
+/// Plans how long an experiment must run given its daily traffic.
+///
+/// Traffic is split across the arms in proportion to `weights`, whose first
+/// entry is the control. Every other arm is compared with the control, and
+/// with more than one treatment `alpha` is divided among the comparisons
+/// unless `bonferroni` is turned off. The plan is set by the comparison with
+/// the least traffic, so every treatment is covered.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::power::{
+///     minimum_detectable_effect, Effect, Planner, PowerOptions, SampleSize,
+/// };
+///
+/// // Half the traffic to the control and a quarter to each treatment.
+/// let planner = Planner::new(0.10, 5000.0).weights(vec![2.0, 1.0, 1.0]);
+/// let rows = planner.table(&[7, 14, 28]).unwrap();
+/// let expected = [(7, 17_500, 0.123865), (14, 35_000, 0.087013), (28, 70_000, 0.061241)];
+/// for (row, (days, control, relative_mde)) in rows.iter().zip(expected) {
+///     assert_eq!(row.days, days);
+///     assert_eq!(row.size, SampleSize { a: control, b: control / 2 });
+///     assert!((row.relative_mde.unwrap() - relative_mde).abs() < 1e-6);
+///
+///     // Each comparison runs at alpha / 2 against a control twice its size.
+///     let options = PowerOptions::default().alpha(0.025);
+///     let mde = minimum_detectable_effect(0.10, row.size, &options).unwrap();
+///     assert_eq!(row.absolute_mde, mde);
+/// }
+///
+/// // A 10% lift needs 11 days: after 10 the detectable lift is still larger.
+/// let days = planner.days_to_detect(Effect::Relative(0.10)).unwrap();
+/// assert_eq!(days, 11);
+/// assert!(planner.mde(days).unwrap().relative_mde.unwrap() <= 0.10);
+/// assert!(planner.mde(days - 1).unwrap().relative_mde.unwrap() > 0.10);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Planner {
+    /// The conversion rate of the control.
+    pub baseline: f64,
+    /// The units entering the experiment each day, across all arms.
+    pub daily_traffic: f64,
+    /// The share of traffic of each arm, starting with the control.
+    pub weights: Vec<f64>,
+    /// The planned test.
+    pub options: PowerOptions,
+    /// Whether to divide `alpha` among several treatments.
+    pub bonferroni: bool,
+}
+
+/// One row of a [`Planner`] table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlanRow {
+    /// The days the experiment runs.
+    pub days: u32,
+    /// The units in the control and in the smallest treatment arm.
+    pub size: SampleSize,
+    /// The minimum detectable difference in rates, if any is detectable.
+    pub absolute_mde: Option<f64>,
+    /// The minimum detectable lift relative to the baseline.
+    pub relative_mde: Option<f64>,
+}
+
+impl Planner {
+    /// Plans an even two-arm experiment with the default [`PowerOptions`].
+    pub fn new(baseline: f64, daily_traffic: f64) -> Self {
+        Planner {
+            baseline,
+            daily_traffic,
+            weights: vec![1.0, 1.0],
+            options: PowerOptions::default(),
+            bonferroni: true,
+        }
+    }
+
+    /// Sets the traffic share of each arm, starting with the control.
+    pub fn weights(mut self, weights: Vec<f64>) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Sets the planned test. Its allocation ratio is ignored in favour of
+    /// the weights.
+    pub fn options(mut self, options: PowerOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets whether to divide `alpha` among several treatments.
+    pub fn bonferroni(mut self, bonferroni: bool) -> Self {
+        self.bonferroni = bonferroni;
+        self
+    }
+
+    /// Checks the baseline, the traffic, the weights and the options.
+    pub fn validate(&self) -> Result<()> {
+        if !(self.baseline > 0.0 && self.baseline < 1.0) {
+            return Err(Error::InvalidProportion(self.baseline));
+        }
+        if !(self.daily_traffic > 0.0 && self.daily_traffic.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "daily_traffic",
+                value: self.daily_traffic,
+            });
+        }
+        if self.weights.len() < 2 {
+            return Err(Error::TooFewGroups {
+                required: 2,
+                actual: self.weights.len(),
+            });
+        }
+        for &weight in &self.weights {
+            if !(weight > 0.0 && weight.is_finite()) {
+                return Err(Error::InvalidParameter {
+                    name: "weights",
+                    value: weight,
+                });
+            }
+        }
+        self.options.validate()
+    }
+
+    /// The minimum detectable effect after running for `days`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Planner::validate`].
+    pub fn mde(&self, days: u32) -> Result<PlanRow> {
+        self.validate()?;
+        let (control, treatment) = self.shares();
+        let units = |share: f64| (self.daily_traffic * days as f64 * share).floor() as u64;
+        let size = SampleSize {
+            a: units(control),
+            b: units(treatment),
+        };
+        let absolute_mde = if size.a.min(size.b) < MIN_SAMPLE_SIZE {
+            None
+        } else {
+            minimum_detectable_effect(self.baseline, size, &self.comparison())?
+        };
+        Ok(PlanRow {
+            days,
+            size,
+            absolute_mde,
+            relative_mde: absolute_mde.map(|d| d / self.baseline),
+        })
+    }
+
+    /// The minimum detectable effect after each of the given durations.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Planner::validate`].
+    pub fn table(&self, days: &[u32]) -> Result<Vec<PlanRow>> {
+        days.iter().map(|&d| self.mde(d)).collect()
+    }
+
+    /// The number of days needed to detect `effect`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the errors of [`Planner::validate`] and [`sample_size`], and
+    /// [`Error::Overflow`] if the duration does not fit in a `u32`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use statistical_computing::power::{Effect, Planner};
+    ///
+    /// let days = Planner::new(0.10, 2000.0).days_to_detect(Effect::Relative(0.10)).unwrap();
+    /// assert_eq!(days, 15);
+    /// ```
+    pub fn days_to_detect(&self, effect: Effect) -> Result<u32> {
+        self.validate()?;
+        let (control, treatment) = self.shares();
+        let size = sample_size(self.baseline, effect, &self.comparison())?;
+        let days = (size.a as f64 / (self.daily_traffic * control))
+            .max(size.b as f64 / (self.daily_traffic * treatment))
+            .ceil();
+        if days > u32::MAX as f64 {
+            return Err(Error::Overflow);
+        }
+        Ok(days as u32)
+    }
+
+    /// The traffic shares of the control and of the smallest treatment.
+    fn shares(&self) -> (f64, f64) {
+        let total: f64 = self.weights.iter().sum();
+        let smallest = self.weights[1..]
+            .iter()
+            .fold(f64::INFINITY, |a, &b| a.min(b));
+        (self.weights[0] / total, smallest / total)
+    }
+
+    /// The options of each comparison with the control.
+    fn comparison(&self) -> PowerOptions {
+        let (control, treatment) = self.shares();
+        let comparisons = (self.weights.len() - 1) as f64;
+        let alpha = if self.bonferroni {
+            self.options.alpha / comparisons
+        } else {
+            self.options.alpha
+        };
+        self.options.alpha(alpha).ratio(treatment / control)
+    }
+}
+
+/// The standard deviations of the difference in rates for one control unit,
+/// with treatment units in proportion.
+struct Plan {
+    difference: f64,
+    null_sd: f64,
+    alternative_sd: f64,
+}
+
+impl Plan {
+    fn new(baseline: f64, effect: Effect, options: &PowerOptions) -> Result<Self> {
+        let p1 = baseline;
+        let p2 = effect.treatment_rate(baseline);
+        for p in [p1, p2] {
+            if !(p > 0.0 && p < 1.0) {
+                return Err(Error::InvalidProportion(p));
+            }
+        }
+        let difference = p2 - p1;
+        let wrong_way = match options.alternative {
+            Alternative::TwoSided => difference == 0.0,
+            Alternative::Greater => difference <= 0.0,
+            Alternative::Less => difference >= 0.0,
+        };
+        if wrong_way {
+            return Err(Error::InvalidParameter {
+                name: "effect",
+                value: difference,
+            });
+        }
+        let k = options.ratio;
+        let pooled = (p1 + k * p2) / (1.0 + k);
+        Ok(Plan {
+            difference,
+            null_sd: (pooled * (1.0 - pooled) * (1.0 + 1.0 / k)).sqrt(),
+            alternative_sd: (p1 * (1.0 - p1) + p2 * (1.0 - p2) / k).sqrt(),
+        })
+    }
+}
@@ -0,0 +1,162 @@
+//! A small seedable random number generator for resampling and simulation.
+//!
+//! The generator is xoshiro256** by Blackman and Vigna, seeded through
+//! splitmix64. It is fast and statistically strong but not cryptographically
+//! secure. Given the same seed it produces the same sequence on every
+//! platform, which keeps bootstrap results and simulations reproducible.
+
+use crate::special::ln_choose;
+
+/// A xoshiro256** generator.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::random::Rng;
+///
+/// let mut first = Rng::new(42);
+/// let mut second = Rng::new(42);
+/// assert_eq!(first.next_u64(), second.next_u64());
+/// let u = first.next_f64();
+/// assert!((0.0..1.0).contains(&u));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    /// Creates a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        let mut x = seed;
+        let mut state = [0; 4];
+        for word in &mut state {
+            *word = splitmix64(&mut x);
+        }
+        Rng { state }
+    }
+
+    /// Creates the generator for one of many independent streams sharing a
+    /// seed, such as one stream per bootstrap resample. Each stream depends
+    /// only on `seed` and `stream`, so work split across threads gives the
+    /// same results however it is divided.
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut x = seed;
+        let base = splitmix64(&mut x);
+        Rng::new(base ^ stream.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
+
+    /// The next 64 random bits.
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// A uniform draw from `[0, 1)` with 53 random bits.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// A uniform draw from `0..n`, without modulo bias.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "cannot draw from an empty range");
+        // Lemire's multiply-and-reject method.
+        let threshold = n.wrapping_neg() % n;
+        loop {
+            let product = u128::from(self.next_u64()) * u128::from(n);
+            if (product as u64) >= threshold {
+                return (product >> 64) as u64;
+            }
+        }
+    }
+
+    /// The number of successes in `trials` independent trials that each
+    /// succeed with probability `p`.
+    ///
+    /// Draws by inversion, searching outwards from the mode, so one uniform
+    /// draw usually suffices and the cost grows with the standard deviation
+    /// `sqrt(trials * p * (1 - p))` rather than with `trials`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` is not in `[0, 1]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use statistical_computing::random::Rng;
+    ///
+    /// let mut rng = Rng::new(7);
+    /// let draws: Vec<u64> = (0..2000).map(|_| rng.binomial(1_000_000, 0.1)).collect();
+    /// let mean = draws.iter().sum::<u64>() as f64 / draws.len() as f64;
+    /// // The standard error of the mean is 300 / sqrt(2000), about 6.7.
+    /// assert!((mean - 100_000.0).abs() < 30.0);
+    /// assert_eq!(rng.binomial(10, 0.0), 0);
+    /// assert_eq!(rng.binomial(10, 1.0), 10);
+    /// ```
+    pub fn binomial(&mut self, trials: u64, p: f64) -> u64 {
+        assert!((0.0..=1.0).contains(&p), "probability {p} is not in [0, 1]");
+        if p > 0.5 {
+            return trials - self.binomial(trials, 1.0 - p);
+        }
+        if trials == 0 || p == 0.0 {
+            return 0;
+        }
+        let (n, q) = (trials as f64, 1.0 - p);
+        let ratio = p / q;
+        let mode = ((n + 1.0) * p).floor().min(n);
+        let at_mode = (ln_choose(n, mode) + mode * p.ln() + (n - mode) * q.ln()).exp();
+        // Rounding can leave the probabilities summing to slightly less than
+        // one, in which case the search runs out of mass and starts again.
+        loop {
+            let mut u = self.next_f64() - at_mode;
+            if u < 0.0 {
+                return mode as u64;
+            }
+            let (mut low, mut low_mass) = (mode, at_mode);
+            let (mut high, mut high_mass) = (mode, at_mode);
+            while low_mass > 0.0 || high_mass > 0.0 {
+                if high < n {
+                    high_mass *= (n - high) / (high + 1.0) * ratio;
+                    high += 1.0;
+                    u -= high_mass;
+                    if u < 0.0 {
+                        return high as u64;
+                    }
+                } else {
+                    high_mass = 0.0;
+                }
+                if low > 0.0 {
+                    low_mass *= low / (n - low + 1.0) / ratio;
+                    low -= 1.0;
+                    u -= low_mass;
+                    if u < 0.0 {
+                        return low as u64;
+                    }
+                } else {
+                    low_mass = 0.0;
+                }
+            }
+        }
+    }
+}
+
+fn splitmix64(x: &mut u64) -> u64 {
+    *x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *x;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
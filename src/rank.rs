@@ -0,0 +1,426 @@
+//! Rank-based tests for skewed continuous metrics such as revenue and
+//! latency.
+//!
+//! The tests use only the ordering of the observations, so a handful of
+//! extreme values cannot dominate them the way they dominate a t-test. The
+//! Mann-Whitney U test asks whether one arm tends to produce larger values
+//! than the other, and the Hodges-Lehmann estimate gives the size of that
+//! shift. The Brunner-Munzel test asks the same question without assuming
+//! that the two distributions have the same shape.
+
+use crate::conversion::{Alternative, Variant};
+use crate::distributions::{normal, student_t};
+use crate::error::{Error, Result};
+use crate::two_sample::ContinuousOptions;
+
+/// The largest arm size for which the exact distribution of U is used.
+const EXACT_LIMIT: usize = 50;
+
+/// The outcome of [`mann_whitney`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MannWhitneyResult {
+    /// The variant whose values tend to be larger, or `None` when the
+    /// difference is not statistically significant.
+    pub winner: Option<Variant>,
+    /// The U statistic of version B: the number of pairs in which B's value
+    /// exceeds A's, counting ties as one half.
+    pub u: f64,
+    /// The standardised statistic of the normal approximation, positive when
+    /// B tends to be larger. It is reported even when the p-value is exact.
+    pub z: f64,
+    /// The p-value under the alternative in the test options.
+    pub p_value: f64,
+    /// The one-sided p-value for the alternative that B tends to be larger.
+    pub p_value_greater: f64,
+    /// The one-sided p-value for the alternative that A tends to be larger.
+    pub p_value_less: f64,
+    /// Whether the p-values come from the exact distribution of U rather
+    /// than the normal approximation.
+    pub exact: bool,
+    /// The Hodges-Lehmann estimate of the shift from A to B, the median of
+    /// the differences `b - a` over all pairs.
+    pub shift: f64,
+    /// Lower bound of the confidence interval for `shift`.
+    pub ci_low: f64,
+    /// Upper bound of the confidence interval for `shift`.
+    pub ci_high: f64,
+}
+
+/// The outcome of [`brunner_munzel`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrunnerMunzelResult {
+    /// The variant whose values tend to be larger, or `None` when the
+    /// difference is not statistically significant.
+    pub winner: Option<Variant>,
+    /// The Brunner-Munzel statistic, positive when B tends to be larger.
+    pub statistic: f64,
+    /// The degrees of freedom of the t distribution the statistic is
+    /// compared with.
+    pub df: f64,
+    /// The p-value under the alternative in the test options.
+    pub p_value: f64,
+    /// The one-sided p-value for the alternative that B tends to be larger.
+    pub p_value_greater: f64,
+    /// The one-sided p-value for the alternative that A tends to be larger.
+    pub p_value_less: f64,
+    /// The estimated relative effect `P(A < B) + P(A = B) / 2`, which is
+    /// one half when neither arm tends to be larger.
+    pub relative_effect: f64,
+    /// Lower bound of the confidence interval for `relative_effect`.
+    pub ci_low: f64,
+    /// Upper bound of the confidence interval for `relative_effect`.
+    pub ci_high: f64,
+}
+
+/// The Mann-Whitney U test, also known as the Wilcoxon rank-sum test, with
+/// the Hodges-Lehmann estimate of the shift between the arms.
+///
+/// When there are no ties and both arms have fewer than 50 observations, the
+/// p-values and the confidence interval come from the exact distribution of
+/// U. Otherwise they use the normal approximation with a correction for ties
+/// and a continuity correction. The confidence interval is Moses' interval,
+/// read off the ordered pairwise differences.
+///
+/// # Errors
+///
+/// Returns [`Error::InsufficientSample`] if an arm is empty,
+/// [`Error::InvalidParameter`] for invalid options or a value that is not a
+/// number, and [`Error::ZeroVariance`] if every value is the same.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::Variant;
+/// use statistical_computing::rank::mann_whitney;
+/// use statistical_computing::two_sample::ContinuousOptions;
+///
+/// let a = [1.2, 0.0, 3.4, 0.8, 2.1, 0.1, 1.7, 0.5];
+/// let b = [2.9, 4.1, 1.9, 12.5, 3.3, 2.6, 5.0, 3.8];
+/// let result = mann_whitney(&a, &b, &ContinuousOptions::default()).unwrap();
+/// assert!(result.exact);
+/// assert_eq!(result.winner, Some(Variant::B));
+/// assert!(result.ci_low > 0.0);
+/// ```
+pub fn mann_whitney(
+    a: &[f64],
+    b: &[f64],
+    options: &ContinuousOptions,
+) -> Result<MannWhitneyResult> {
+    options.validate()?;
+    check(a, b, 1)?;
+    let (na, nb) = (a.len(), b.len());
+    let ranked = Ranks::new(a, b);
+    let pairs = (na * nb) as f64;
+    let u = ranked.sum_b - (nb * (nb + 1)) as f64 / 2.0;
+    let mean = pairs / 2.0;
+    let total = (na + nb) as f64;
+    let variance = pairs / 12.0 * ((total + 1.0) - ranked.ties / (total * (total - 1.0)));
+    if variance <= 0.0 {
+        return Err(Error::ZeroVariance);
+    }
+    let sd = variance.sqrt();
+    let z = (u - mean) / sd;
+
+    let tail = match options.alternative {
+        Alternative::TwoSided => (1.0 - options.confidence) / 2.0,
+        Alternative::Greater | Alternative::Less => 1.0 - options.confidence,
+    };
+    let exact = ranked.ties == 0.0 && na < EXACT_LIMIT && nb < EXACT_LIMIT;
+    let (p_value_greater, p_value_less, k) = if exact {
+        let distribution = u_distribution(na, nb);
+        let at_most = |u: usize| distribution[..=u].iter().sum::<f64>().min(1.0);
+        let observed = u.round() as usize;
+        let greater = distribution[observed..].iter().sum::<f64>().min(1.0);
+        // The number of smallest differences excluded from each side.
+        let k = (0..distribution.len())
+            .take_while(|&c| at_most(c) <= tail)
+            .count();
+        (greater, at_most(observed), k)
+    } else {
+        let greater = normal::sf((u - mean - 0.5) / sd);
+        let less = normal::cdf((u - mean + 0.5) / sd);
+        let k = (mean + 0.5 - normal::quantile(1.0 - tail) * sd)
+            .floor()
+            .max(0.0) as usize;
+        (greater, less, k)
+    };
+    let p_value = match options.alternative {
+        Alternative::TwoSided => (2.0 * p_value_greater.min(p_value_less)).min(1.0),
+        Alternative::Greater => p_value_greater,
+        Alternative::Less => p_value_less,
+    };
+
+    let differences = PairwiseDifferences::new(a, b);
+    let count = na * nb;
+    let shift = if count % 2 == 1 {
+        differences.nth(count / 2 + 1)
+    } else {
+        0.5 * (differences.nth(count / 2) + differences.nth(count / 2 + 1))
+    };
+    let (lower, upper) = if k == 0 || k > count {
+        (f64::NEG_INFINITY, f64::INFINITY)
+    } else {
+        (differences.nth(k), differences.nth(count + 1 - k))
+    };
+    let (ci_low, ci_high) = match options.alternative {
+        Alternative::TwoSided => (lower, upper),
+        Alternative::Greater => (lower, f64::INFINITY),
+        Alternative::Less => (f64::NEG_INFINITY, upper),
+    };
+
+    Ok(MannWhitneyResult {
+        winner: winner(p_value, z, options.alpha),
+        u,
+        z,
+        p_value,
+        p_value_greater,
+        p_value_less,
+        exact,
+        shift,
+        ci_low,
+        ci_high,
+    })
+}
+
+/// The Brunner-Munzel test for stochastic equality, which unlike the
+/// Mann-Whitney test stays valid when the arms differ in spread or shape.
+///
+/// The statistic is compared with a t distribution whose degrees of freedom
+/// follow a Satterthwaite approximation.
+///
+/// # Errors
+///
+/// Returns [`Error::InsufficientSample`] if an arm has fewer than two
+/// observations, [`Error::InvalidParameter`] for invalid options or a value
+/// that is not a number, and [`Error::ZeroVariance`] if the ranks do not
+/// vary within either arm, as happens when the arms are completely
+/// separated.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::rank::brunner_munzel;
+/// use statistical_computing::two_sample::ContinuousOptions;
+///
+/// let a = [1.0, 2.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 2.0, 4.0, 1.0, 1.0];
+/// let b = [3.0, 3.0, 4.0, 3.0, 1.0, 2.0, 3.0, 1.0, 1.0, 5.0, 4.0];
+/// let result = brunner_munzel(&a, &b, &ContinuousOptions::default()).unwrap();
+/// assert!((result.statistic - 3.1375).abs() < 1e-4);
+/// assert!((result.p_value - 0.0057).abs() < 1e-4);
+/// ```
+pub fn brunner_munzel(
+    a: &[f64],
+    b: &[f64],
+    options: &ContinuousOptions,
+) -> Result<BrunnerMunzelResult> {
+    options.validate()?;
+    check(a, b, 2)?;
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+    let pooled = Ranks::new(a, b);
+    let within_a = Ranks::new(a, &[]);
+    let within_b = Ranks::new(b, &[]);
+    let mean_a = pooled.ranks[..a.len()].iter().sum::<f64>() / na;
+    let mean_b = pooled.sum_b / nb;
+    let spread = |pooled: &[f64], within: &[f64], mean: f64, n: f64| {
+        pooled
+            .iter()
+            .zip(within)
+            .map(|(r, w)| (r - w - mean + (n + 1.0) / 2.0).powi(2))
+            .sum::<f64>()
+            / (n - 1.0)
+    };
+    let sa = spread(&pooled.ranks[..a.len()], &within_a.ranks, mean_a, na);
+    let sb = spread(&pooled.ranks[a.len()..], &within_b.ranks, mean_b, nb);
+    let scale = na * sa + nb * sb;
+    if scale <= 0.0 {
+        return Err(Error::ZeroVariance);
+    }
+    let relative_effect = (mean_b - (nb + 1.0) / 2.0) / na;
+    let standard_error = scale.sqrt() / (na * nb);
+    let statistic = (relative_effect - 0.5) / standard_error;
+    let df = scale * scale / ((na * sa).powi(2) / (na - 1.0) + (nb * sb).powi(2) / (nb - 1.0));
+
+    let p_value_greater = student_t::sf(statistic, df);
+    let p_value_less = student_t::cdf(statistic, df);
+    let margin = |level: f64| student_t::quantile(level, df) * standard_error;
+    let (p_value, ci_low, ci_high) = match options.alternative {
+        Alternative::TwoSided => {
+            let m = margin((1.0 + options.confidence) / 2.0);
+            (
+                (2.0 * p_value_greater.min(p_value_less)).min(1.0),
+                relative_effect - m,
+                relative_effect + m,
+            )
+        }
+        Alternative::Greater => (
+            p_value_greater,
+            relative_effect - margin(options.confidence),
+            1.0,
+        ),
+        Alternative::Less => (
+            p_value_less,
+            0.0,
+            relative_effect + margin(options.confidence),
+        ),
+    };
+
+    Ok(BrunnerMunzelResult {
+        winner: winner(p_value, statistic, options.alpha),
+        statistic,
+        df,
+        p_value,
+        p_value_greater,
+        p_value_less,
+        relative_effect,
+        ci_low: ci_low.max(0.0),
+        ci_high: ci_high.min(1.0),
+    })
+}
+
+fn winner(p_value: f64, statistic: f64, alpha: f64) -> Option<Variant> {
+    if p_value >= alpha {
+        None
+    } else if statistic > 0.0 {
+        Some(Variant::B)
+    } else {
+        Some(Variant::A)
+    }
+}
+
+fn check(a: &[f64], b: &[f64], required: usize) -> Result<()> {
+    let smallest = a.len().min(b.len());
+    if smallest < required {
+        return Err(Error::InsufficientSample {
+            required: required as u64,
+            actual: smallest as u64,
+        });
+    }
+    if let Some(&value) = a.iter().chain(b).find(|x| x.is_nan()) {
+        return Err(Error::InvalidParameter {
+            name: "value",
+            value,
+        });
+    }
+    Ok(())
+}
+
+/// Midranks of the values of both arms taken together.
+struct Ranks {
+    /// The rank of each value, A's values first.
+    ranks: Vec<f64>,
+    /// The sum of the ranks of B's values.
+    sum_b: f64,
+    /// The tie term `sum(t^3 - t)` over groups of `t` tied values.
+    ties: f64,
+}
+
+impl Ranks {
+    fn new(a: &[f64], b: &[f64]) -> Self {
+        let values: Vec<f64> = a.iter().chain(b).copied().collect();
+        let mut order: Vec<usize> = (0..values.len()).collect();
+        order.sort_by(|&i, &j| values[i].total_cmp(&values[j]));
+        let mut ranks = vec![0.0; values.len()];
+        let mut ties = 0.0;
+        let mut start = 0;
+        while start < order.len() {
+            let mut end = start + 1;
+            while end < order.len() && values[order[end]] == values[order[start]] {
+                end += 1;
+            }
+            let rank = (start + end + 1) as f64 / 2.0;
+            for &i in &order[start..end] {
+                ranks[i] = rank;
+            }
+            let t = (end - start) as f64;
+            ties += t * t * t - t;
+            start = end;
+        }
+        let sum_b = ranks[a.len()..].iter().sum();
+        Ranks { ranks, sum_b, ties }
+    }
+}
+
+/// The exact null distribution of U for arms of sizes `na` and `nb` without
+/// ties, as probabilities indexed by U.
+fn u_distribution(na: usize, nb: usize) -> Vec<f64> {
+    let total = na + nb;
+    let max_sum = nb * (2 * total - nb + 1) / 2;
+    // ways[k][s]: the number of ways to give B k of the ranks seen so far
+    // with rank sum s.
+    let mut ways = vec![vec![0.0f64; max_sum + 1]; nb + 1];
+    ways[0][0] = 1.0;
+    for rank in 1..=total {
+        for k in (1..=nb.min(rank)).rev() {
+            let (lower, upper) = ways.split_at_mut(k);
+            for s in (rank..=max_sum).rev() {
+                upper[0][s] += lower[k - 1][s - rank];
+            }
+        }
+    }
+    let offset = nb * (nb + 1) / 2;
+    let counts = &ways[nb][offset..=offset + na * nb];
+    let sum: f64 = counts.iter().sum();
+    counts.iter().map(|c| c / sum).collect()
+}
+
+/// Order statistics of the differences `b - a` over all pairs, found without
+/// listing the pairs.
+struct PairwiseDifferences {
+    a: Vec<f64>,
+    b: Vec<f64>,
+}
+
+impl PairwiseDifferences {
+    fn new(a: &[f64], b: &[f64]) -> Self {
+        let mut a = a.to_vec();
+        let mut b = b.to_vec();
+        a.sort_by(f64::total_cmp);
+        b.sort_by(f64::total_cmp);
+        PairwiseDifferences { a, b }
+    }
+
+    /// The number of pairs with `b - a <= d`.
+    fn count_at_most(&self, d: f64) -> usize {
+        // For increasing b, the values of a with b - a <= d form a shrinking
+        // suffix. Comparing the rounded differences themselves keeps the
+        // bisection in `nth` converging onto an actual difference.
+        let mut start = 0;
+        let mut count = 0;
+        for &b in &self.b {
+            while start < self.a.len() && b - self.a[start] > d {
+                start += 1;
+            }
+            count += self.a.len() - start;
+        }
+        count
+    }
+
+    /// The `k`-th smallest difference, counting from 1.
+    fn nth(&self, k: usize) -> f64 {
+        let mut low = self.b[0] - self.a[self.a.len() - 1];
+        let mut high = self.b[self.b.len() - 1] - self.a[0];
+        if self.count_at_most(low) >= k {
+            return low;
+        }
+        for _ in 0..200 {
+            let mid = 0.5 * (low + high);
+            if mid <= low || mid >= high {
+                break;
+            }
+            if self.count_at_most(mid) >= k {
+                high = mid;
+            } else {
+                low = mid;
+            }
+        }
+        // Near zero the bisection can run out of iterations before `high`
+        // reaches a difference, so return the smallest one above `low`.
+        self.b
+            .iter()
+            .filter_map(|&b| {
+                let above = self.a.partition_point(|&a| b - a > low);
+                (above > 0).then(|| b - self.a[above - 1])
+            })
+            .fold(high, f64::min)
+    }
+}
@@ -0,0 +1,248 @@
+//! Tests for ratio metrics such as clicks per session or revenue per order.
+//!
+//! In a ratio metric both the numerator and the denominator vary from unit
+//! to unit, while randomisation happens per unit. Treating every session or
+//! order as an independent trial, as [`ab_conversion_test`] would, ignores
+//! the correlation between a unit's events and understates the variance.
+//! The tests here work from per-unit sums instead and approximate the
+//! variance of the ratio of means with the delta method, including the
+//! covariance between numerator and denominator.
+//!
+//! [`ab_conversion_test`]: crate::conversion::ab_conversion_test
+
+use crate::conversion::{Alternative, Variant};
+use crate::distributions::normal;
+use crate::error::{Error, Result};
+use crate::two_sample::ContinuousOptions;
+
+/// Per-unit moments of a ratio metric in one arm.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RatioSummary {
+    /// The number of units.
+    pub n: u64,
+    /// The mean per-unit numerator, such as clicks per user.
+    pub numerator_mean: f64,
+    /// The mean per-unit denominator, such as sessions per user.
+    pub denominator_mean: f64,
+    /// The sample variance of the per-unit numerators.
+    pub numerator_variance: f64,
+    /// The sample variance of the per-unit denominators.
+    pub denominator_variance: f64,
+    /// The sample covariance of numerator and denominator.
+    pub covariance: f64,
+}
+
+impl RatioSummary {
+    /// Summarises per-unit numerators and denominators, given in the same
+    /// unit order.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] if the slices differ in length.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use statistical_computing::ratio::RatioSummary;
+    ///
+    /// let clicks = [3.0, 0.0, 5.0, 1.0];
+    /// let sessions = [2.0, 1.0, 4.0, 1.0];
+    /// let summary = RatioSummary::from_units(&clicks, &sessions).unwrap();
+    /// assert_eq!(summary.ratio(), 9.0 / 8.0);
+    /// ```
+    pub fn from_units(numerators: &[f64], denominators: &[f64]) -> Result<Self> {
+        if numerators.len() != denominators.len() {
+            return Err(Error::InvalidParameter {
+                name: "denominators",
+                value: denominators.len() as f64,
+            });
+        }
+        let n = numerators.len();
+        let mut summary = RatioSummary {
+            n: n as u64,
+            ..RatioSummary::default()
+        };
+        if n == 0 {
+            return Ok(summary);
+        }
+        let (mut co_x, mut co_y, mut co_xy) = (0.0, 0.0, 0.0);
+        let (mut mx, mut my) = (0.0, 0.0);
+        for (i, (&x, &y)) in numerators.iter().zip(denominators).enumerate() {
+            let dx = x - mx;
+            let dy = y - my;
+            mx += dx / (i + 1) as f64;
+            my += dy / (i + 1) as f64;
+            co_x += dx * (x - mx);
+            co_y += dy * (y - my);
+            co_xy += dx * (y - my);
+        }
+        summary.numerator_mean = mx;
+        summary.denominator_mean = my;
+        if n > 1 {
+            let df = (n - 1) as f64;
+            summary.numerator_variance = co_x / df;
+            summary.denominator_variance = co_y / df;
+            summary.covariance = co_xy / df;
+        }
+        Ok(summary)
+    }
+
+    /// The ratio of means, which equals the ratio of the arm's totals.
+    pub fn ratio(&self) -> f64 {
+        self.numerator_mean / self.denominator_mean
+    }
+
+    /// The delta-method variance of [`RatioSummary::ratio`].
+    pub fn ratio_variance(&self) -> f64 {
+        let (mx, my) = (self.numerator_mean, self.denominator_mean);
+        (self.numerator_variance / (my * my) - 2.0 * mx * self.covariance / my.powi(3)
+            + mx * mx * self.denominator_variance / my.powi(4))
+            / self.n as f64
+    }
+
+    /// Checks that there are at least two units, that the mean denominator
+    /// is positive, and that every moment is finite.
+    pub fn validate(&self) -> Result<()> {
+        if self.n < 2 {
+            return Err(Error::InsufficientSample {
+                required: 2,
+                actual: self.n,
+            });
+        }
+        for (name, value) in [
+            ("numerator_mean", self.numerator_mean),
+            ("denominator_mean", self.denominator_mean),
+            ("numerator_variance", self.numerator_variance),
+            ("denominator_variance", self.denominator_variance),
+            ("covariance", self.covariance),
+        ] {
+            if !value.is_finite() {
+                return Err(Error::InvalidParameter { name, value });
+            }
+        }
+        if self.denominator_mean <= 0.0 {
+            return Err(Error::InvalidParameter {
+                name: "denominator_mean",
+                value: self.denominator_mean,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of [`ratio_test`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RatioTestResult {
+    /// The variant with the higher ratio, or `None` when the difference is
+    /// not statistically significant.
+    pub winner: Option<Variant>,
+    /// The z statistic, signed like `difference`.
+    pub z: f64,
+    /// The p-value under the alternative in the test options.
+    pub p_value: f64,
+    /// The one-sided p-value for the alternative that B's ratio is higher.
+    pub p_value_greater: f64,
+    /// The one-sided p-value for the alternative that A's ratio is higher.
+    pub p_value_less: f64,
+    /// The ratio of version A.
+    pub ratio_a: f64,
+    /// The ratio of version B.
+    pub ratio_b: f64,
+    /// The difference of ratios, `ratio_b - ratio_a`.
+    pub difference: f64,
+    /// Lower bound of the confidence interval for `difference`.
+    pub ci_low: f64,
+    /// Upper bound of the confidence interval for `difference`.
+    pub ci_high: f64,
+    /// The relative lift `ratio_b / ratio_a - 1`.
+    pub relative_lift: f64,
+    /// Lower bound of the confidence interval for `relative_lift`.
+    pub relative_ci_low: f64,
+    /// Upper bound of the confidence interval for `relative_lift`.
+    pub relative_ci_high: f64,
+}
+
+/// Tests for a difference in a ratio metric between two arms.
+///
+/// The variance of each arm's ratio comes from the delta method, and the
+/// variance of the relative lift from a second application of it to the
+/// ratio of the two ratios.
+///
+/// # Errors
+///
+/// Returns [`Error::InsufficientSample`] if an arm has fewer than two
+/// units, [`Error::InvalidParameter`] for invalid options or summaries, and
+/// [`Error::ZeroVariance`] if neither ratio varies.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::ratio::{ratio_test, RatioSummary};
+/// use statistical_computing::two_sample::ContinuousOptions;
+///
+/// let a = RatioSummary {
+///     n: 5000,
+///     numerator_mean: 2.0,
+///     denominator_mean: 4.0,
+///     numerator_variance: 6.0,
+///     denominator_variance: 9.0,
+///     covariance: 5.0,
+/// };
+/// let b = RatioSummary { numerator_mean: 2.2, ..a };
+/// let result = ratio_test(a, b, &ContinuousOptions::default()).unwrap();
+/// assert!((result.relative_lift - 0.1).abs() < 1e-12);
+/// assert!(result.winner.is_some());
+/// ```
+pub fn ratio_test(
+    a: RatioSummary,
+    b: RatioSummary,
+    options: &ContinuousOptions,
+) -> Result<RatioTestResult> {
+    options.validate()?;
+    a.validate()?;
+    b.validate()?;
+    let (ratio_a, ratio_b) = (a.ratio(), b.ratio());
+    let (va, vb) = (a.ratio_variance().max(0.0), b.ratio_variance().max(0.0));
+    let standard_error = (va + vb).sqrt();
+    if standard_error <= 0.0 {
+        return Err(Error::ZeroVariance);
+    }
+    let difference = ratio_b - ratio_a;
+    let z = difference / standard_error;
+
+    let relative_lift = ratio_b / ratio_a - 1.0;
+    let relative_error =
+        (ratio_b / ratio_a).abs() * (vb / (ratio_b * ratio_b) + va / (ratio_a * ratio_a)).sqrt();
+    let interval = |estimate: f64, error: f64| match options.alternative {
+        Alternative::TwoSided => {
+            let m = normal::quantile((1.0 + options.confidence) / 2.0) * error;
+            (estimate - m, estimate + m)
+        }
+        Alternative::Greater => (
+            estimate - normal::quantile(options.confidence) * error,
+            f64::INFINITY,
+        ),
+        Alternative::Less => (
+            f64::NEG_INFINITY,
+            estimate + normal::quantile(options.confidence) * error,
+        ),
+    };
+    let (ci_low, ci_high) = interval(difference, standard_error);
+    let (relative_ci_low, relative_ci_high) = interval(relative_lift, relative_error);
+
+    Ok(RatioTestResult {
+        winner: options.alternative.winner(z, options.alpha),
+        z,
+        p_value: options.alternative.p_value(z),
+        p_value_greater: Alternative::Greater.p_value(z),
+        p_value_less: Alternative::Less.p_value(z),
+        ratio_a,
+        ratio_b,
+        difference,
+        ci_low,
+        ci_high,
+        relative_lift,
+        relative_ci_low,
+        relative_ci_high,
+    })
+}
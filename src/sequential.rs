@@ -0,0 +1,255 @@
+//! Always-valid sequential testing for monitoring an experiment as data
+//! arrives.
+//!
+//! Re-running a fixed-horizon test such as [`ab_conversion_test`] at every
+//! look inflates the false positive rate far beyond `alpha`. The mixture
+//! sequential probability ratio test (mSPRT) of Johari et al. instead reports
+//! a p-value that is valid however often, and whenever, it is checked, along
+//! with the matching anytime-valid confidence sequence for the difference
+//! `p2 - p1`.
+//!
+//! [`ab_conversion_test`]: crate::conversion::ab_conversion_test
+
+use crate::conversion::{Arm, Variant};
+use crate::error::{Error, Result};
+
+/// Settings for a [`SequentialTest`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SequentialOptions {
+    /// The significance level, which bounds the chance of ever rejecting a
+    /// true null hypothesis across all looks.
+    pub alpha: f64,
+    /// The variance `tau^2` of the normal mixture over the difference in
+    /// rates. It should be on the order of the squared effect size the test
+    /// is meant to detect; the default of `1e-4` suits lifts of about one
+    /// percentage point.
+    pub mixing_variance: f64,
+}
+
+impl Default for SequentialOptions {
+    fn default() -> Self {
+        SequentialOptions {
+            alpha: 0.05,
+            mixing_variance: 1e-4,
+        }
+    }
+}
+
+impl SequentialOptions {
+    /// Sets the significance level.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the variance of the mixing distribution.
+    pub fn mixing_variance(mut self, mixing_variance: f64) -> Self {
+        self.mixing_variance = mixing_variance;
+        self
+    }
+
+    /// Checks that `alpha` lies in `(0, 1)` and the mixing variance is
+    /// positive.
+    pub fn validate(&self) -> Result<()> {
+        if !(self.alpha > 0.0 && self.alpha < 1.0) {
+            return Err(Error::InvalidParameter {
+                name: "alpha",
+                value: self.alpha,
+            });
+        }
+        if !(self.mixing_variance > 0.0 && self.mixing_variance.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "mixing_variance",
+                value: self.mixing_variance,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The state of a [`SequentialTest`] after a look.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SequentialResult {
+    /// The number of looks taken so far.
+    pub looks: usize,
+    /// The cumulative counts of version A.
+    pub a: Arm,
+    /// The cumulative counts of version B.
+    pub b: Arm,
+    /// The observed difference `p2 - p1`.
+    pub difference: f64,
+    /// The mixture likelihood ratio at this look, or 1 when the look carries
+    /// no evidence because the estimated variance of the difference is zero.
+    pub likelihood_ratio: f64,
+    /// The always-valid p-value, the smallest seen at any look.
+    pub p_value: f64,
+    /// Lower bound of the confidence sequence, the intersection of the
+    /// intervals of every look.
+    ///
+    /// The intersection is empty, with `ci_low > ci_high`, once two looks
+    /// disagree so strongly that no single difference fits both, as when the
+    /// effect drifts over the course of the experiment. Every difference,
+    /// zero included, has then been rejected, so the p-value is below
+    /// `alpha` and the test stays rejected.
+    pub ci_low: f64,
+    /// Upper bound of the confidence sequence.
+    pub ci_high: f64,
+    /// The variant the confidence sequence favours once it excludes zero.
+    /// When the confidence sequence is empty, this is the variant favoured
+    /// by the observed difference at the latest look.
+    pub winner: Option<Variant>,
+}
+
+/// An mSPRT for the difference of two conversion rates, updated one batch
+/// of data at a time.
+///
+/// The test uses the normal approximation to the difference of rates, with
+/// a normal mixture of variance `tau^2` over the alternative. With `V` the
+/// estimated variance of the difference `d`, the likelihood ratio against
+/// `p2 - p1 = 0` is
+///
+/// `sqrt(V / (V + tau^2)) * exp(tau^2 d^2 / (2 V (V + tau^2)))`,
+///
+/// and the p-value at each look is the running minimum of its reciprocal.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::Arm;
+/// use statistical_computing::sequential::{SequentialOptions, SequentialTest};
+///
+/// let mut test = SequentialTest::new(SequentialOptions::default()).unwrap();
+/// for _day in 0..14 {
+///     test.update(Arm::new(100, 1000), Arm::new(130, 1000)).unwrap();
+/// }
+/// let result = test.result();
+/// assert!(result.p_value < 0.05);
+/// assert!(result.ci_low > 0.0);
+/// ```
+///
+/// An effect that reverses leaves the confidence sequence empty, and the
+/// test rejected in favour of the variant now ahead:
+///
+/// ```
+/// use statistical_computing::conversion::{Arm, Variant};
+/// use statistical_computing::sequential::{SequentialOptions, SequentialTest};
+///
+/// let mut test = SequentialTest::new(SequentialOptions::default()).unwrap();
+/// test.update(Arm::new(100, 2000), Arm::new(300, 2000)).unwrap();
+/// let result = test.update(Arm::new(12_000, 100_000), Arm::new(8_000, 100_000)).unwrap();
+/// assert!(result.ci_low > result.ci_high);
+/// assert!(result.p_value < 0.05);
+/// assert_eq!(result.winner, Some(Variant::A));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequentialTest {
+    options: SequentialOptions,
+    result: SequentialResult,
+}
+
+impl SequentialTest {
+    /// Starts a test with no data.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidParameter`] for invalid options.
+    pub fn new(options: SequentialOptions) -> Result<Self> {
+        options.validate()?;
+        Ok(SequentialTest {
+            options,
+            result: SequentialResult {
+                looks: 0,
+                a: Arm::default(),
+                b: Arm::default(),
+                difference: 0.0,
+                likelihood_ratio: 1.0,
+                p_value: 1.0,
+                ci_low: -1.0,
+                ci_high: 1.0,
+                winner: None,
+            },
+        })
+    }
+
+    /// The state after the most recent look.
+    pub fn result(&self) -> SequentialResult {
+        self.result
+    }
+
+    /// Adds the conversions observed since the previous look and takes a new
+    /// look.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SuccessesExceedTrials`] for an inconsistent batch and
+    /// [`Error::Overflow`] if the cumulative counts overflow. The state is
+    /// unchanged on error.
+    ///
+    /// A look at which either version has no units, or the estimated variance
+    /// of the difference is zero, updates the counts but leaves the p-value
+    /// and confidence sequence as they were.
+    pub fn update(&mut self, a: Arm, b: Arm) -> Result<SequentialResult> {
+        a.validate()?;
+        b.validate()?;
+        let add = |total: Arm, batch: Arm| -> Result<Arm> {
+            Ok(Arm::new(
+                total
+                    .successes
+                    .checked_add(batch.successes)
+                    .ok_or(Error::Overflow)?,
+                total
+                    .trials
+                    .checked_add(batch.trials)
+                    .ok_or(Error::Overflow)?,
+            ))
+        };
+        let total_a = add(self.result.a, a)?;
+        let total_b = add(self.result.b, b)?;
+
+        let result = &mut self.result;
+        result.looks += 1;
+        result.a = total_a;
+        result.b = total_b;
+        if total_a.trials == 0 || total_b.trials == 0 {
+            return Ok(*result);
+        }
+        let (p1, p2) = (total_a.rate(), total_b.rate());
+        let variance =
+            p1 * (1.0 - p1) / total_a.trials as f64 + p2 * (1.0 - p2) / total_b.trials as f64;
+        result.difference = p2 - p1;
+        if variance <= 0.0 {
+            result.likelihood_ratio = 1.0;
+            return Ok(*result);
+        }
+
+        let tau2 = self.options.mixing_variance;
+        let d = result.difference;
+        let ln_ratio = 0.5 * (variance / (variance + tau2)).ln()
+            + tau2 * d * d / (2.0 * variance * (variance + tau2));
+        result.likelihood_ratio = ln_ratio.exp();
+        result.p_value = result.p_value.min((-ln_ratio).exp().min(1.0));
+
+        // The differences whose likelihood ratio stays below 1 / alpha.
+        let radius = (2.0 * variance * (variance + tau2) / tau2
+            * ((1.0 / self.options.alpha).ln() + 0.5 * ((variance + tau2) / variance).ln()))
+        .sqrt();
+        result.ci_low = result.ci_low.max(d - radius);
+        result.ci_high = result.ci_high.min(d + radius);
+        result.winner = if result.ci_low > result.ci_high {
+            if d > 0.0 {
+                Some(Variant::B)
+            } else if d < 0.0 {
+                Some(Variant::A)
+            } else {
+                None
+            }
+        } else if result.ci_low > 0.0 {
+            Some(Variant::B)
+        } else if result.ci_high < 0.0 {
+            Some(Variant::A)
+        } else {
+            None
+        };
+        Ok(*result)
+    }
+}
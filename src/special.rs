@@ -0,0 +1,382 @@
+//! Special functions underlying the probability distributions.
+
+// Coefficients are kept exactly as published.
+#![allow(clippy::excessive_precision)]
+
+/// Coefficients for `erf` on `|x| <= 0.5`.
+const ERF_A: [f64; 5] = [
+    3.161_123_743_870_565_6,
+    1.138_641_541_510_501_6e2,
+    3.774_852_376_853_020_2e2,
+    3.209_377_589_138_469_5e3,
+    1.857_777_061_846_031_5e-1,
+];
+const ERF_B: [f64; 4] = [
+    2.360_129_095_234_412_1e1,
+    2.440_246_379_344_441_7e2,
+    1.282_616_526_077_372_3e3,
+    2.844_236_833_439_170_6e3,
+];
+/// Coefficients for `erfc` on `0.5 < |x| <= 4`.
+const ERF_C: [f64; 9] = [
+    5.641_884_969_886_700_9e-1,
+    8.883_149_794_388_376,
+    6.611_919_063_714_163e1,
+    2.986_351_381_974_001_3e2,
+    8.819_522_212_417_691e2,
+    1.712_047_612_634_070_6e3,
+    2.051_078_377_826_071_5e3,
+    1.230_339_354_797_997_2e3,
+    2.153_115_354_744_038_5e-8,
+];
+const ERF_D: [f64; 8] = [
+    1.574_492_611_070_983_5e1,
+    1.176_939_508_913_125e2,
+    5.371_811_018_620_098_6e2,
+    1.621_389_574_566_690_2e3,
+    3.290_799_235_733_459_6e3,
+    4.362_619_090_143_247e3,
+    3.439_367_674_143_721_6e3,
+    1.230_339_354_803_749_4e3,
+];
+/// Coefficients for `erfc` on `|x| > 4`.
+const ERF_P: [f64; 6] = [
+    3.053_266_349_612_323_4e-1,
+    3.603_448_999_498_044_4e-1,
+    1.257_817_261_112_292_5e-1,
+    1.608_378_514_874_227_7e-2,
+    6.587_491_615_298_378e-4,
+    1.631_538_713_730_209_8e-2,
+];
+const ERF_Q: [f64; 5] = [
+    2.568_520_192_289_822,
+    1.872_952_849_923_467_3,
+    5.279_051_029_514_284e-1,
+    6.051_834_131_244_132e-2,
+    2.335_204_976_268_691_8e-3,
+];
+
+/// The error function.
+///
+/// Uses W. J. Cody's rational Chebyshev approximations, which are accurate to
+/// roughly double precision over the whole real line.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::special::erf;
+///
+/// assert!((erf(0.5) - 0.5204998778130465).abs() < 1e-15);
+/// assert!((erf(-0.5) + 0.5204998778130465).abs() < 1e-15);
+/// assert_eq!(erf(0.0), 0.0);
+/// ```
+pub fn erf(x: f64) -> f64 {
+    if x.is_nan() {
+        return f64::NAN;
+    }
+    if x.abs() <= 0.468_75 {
+        x * small(x.abs())
+    } else if x > 0.0 {
+        1.0 - large(x)
+    } else {
+        large(-x) - 1.0
+    }
+}
+
+/// The complementary error function `1 - erf(x)`.
+///
+/// Unlike computing `1.0 - erf(x)` directly, this keeps full relative
+/// accuracy far into the upper tail, which is where small p-values live.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::special::erfc;
+///
+/// assert!((erfc(0.5) - 0.4795001221869535).abs() < 1e-15);
+/// // Deep in the tail the error is relative, not absolute.
+/// let tail = 1.5374597944280351e-12;
+/// assert!((erfc(5.0) - tail).abs() < 1e-14 * tail);
+/// assert!((erfc(10.0) - 2.088487583762545e-45).abs() < 1e-58);
+/// ```
+pub fn erfc(x: f64) -> f64 {
+    if x.is_nan() {
+        return f64::NAN;
+    }
+    if x.abs() <= 0.468_75 {
+        1.0 - x * small(x.abs())
+    } else if x > 0.0 {
+        large(x)
+    } else {
+        2.0 - large(-x)
+    }
+}
+
+/// `erf(y) / y` for `0 <= y <= 0.46875`.
+fn small(y: f64) -> f64 {
+    let ysq = if y > f64::EPSILON / 2.0 { y * y } else { 0.0 };
+    let mut num = ERF_A[4] * ysq;
+    let mut den = ysq;
+    for i in 0..3 {
+        num = (num + ERF_A[i]) * ysq;
+        den = (den + ERF_B[i]) * ysq;
+    }
+    (num + ERF_A[3]) / (den + ERF_B[3])
+}
+
+/// `erfc(y)` for `y > 0.46875`.
+fn large(y: f64) -> f64 {
+    let scaled = if y <= 4.0 {
+        let mut num = ERF_C[8] * y;
+        let mut den = y;
+        for i in 0..7 {
+            num = (num + ERF_C[i]) * y;
+            den = (den + ERF_D[i]) * y;
+        }
+        (num + ERF_C[7]) / (den + ERF_D[7])
+    } else {
+        if y >= 26.543 {
+            return 0.0;
+        }
+        let ysq = 1.0 / (y * y);
+        let mut num = ERF_P[5] * ysq;
+        let mut den = ysq;
+        for i in 0..4 {
+            num = (num + ERF_P[i]) * ysq;
+            den = (den + ERF_Q[i]) * ysq;
+        }
+        let r = ysq * (num + ERF_P[4]) / (den + ERF_Q[4]);
+        (std::f64::consts::FRAC_2_SQRT_PI / 2.0 - r) / y
+    };
+    // Split y^2 so exp(-y^2) does not lose precision to rounding.
+    let ysq = (y * 16.0).trunc() / 16.0;
+    let del = (y - ysq) * (y + ysq);
+    (-ysq * ysq).exp() * (-del).exp() * scaled
+}
+
+/// Coefficients of the Lanczos approximation with `g = 7`.
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_93,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_13,
+    -176.615_029_162_140_59,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_571_6e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// The natural logarithm of the absolute value of the gamma function.
+///
+/// Uses the Lanczos approximation, with the reflection formula for
+/// arguments below one half.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::special::ln_gamma;
+///
+/// // Γ(1/2) = √π and Γ(10) = 9!.
+/// assert!((ln_gamma(0.5) - 0.5723649429247001).abs() < 1e-13);
+/// assert!((ln_gamma(10.0) - 362_880f64.ln()).abs() < 1e-12);
+/// assert!(ln_gamma(1.0).abs() < 1e-14);
+/// ```
+pub fn ln_gamma(x: f64) -> f64 {
+    if x < 0.5 {
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin().abs()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let mut sum = LANCZOS[0];
+    for (i, c) in LANCZOS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// The natural logarithm of the binomial coefficient `n choose k`.
+pub fn ln_choose(n: f64, k: f64) -> f64 {
+    ln_gamma(n + 1.0) - ln_gamma(k + 1.0) - ln_gamma(n - k + 1.0)
+}
+
+/// The regularized lower incomplete gamma function `P(a, x)`.
+///
+/// Uses the power series for `x < a + 1` and the continued fraction of the
+/// complement otherwise, as in Numerical Recipes.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::special::gamma_p;
+///
+/// // P(1, x) = 1 - exp(-x).
+/// assert!((gamma_p(1.0, 2.0) - 0.8646647167633873).abs() < 1e-14);
+/// assert!((gamma_p(3.0, 2.5) - 0.4561868841166705).abs() < 1e-13);
+/// ```
+pub fn gamma_p(a: f64, x: f64) -> f64 {
+    if x.is_nan() || a.is_nan() || a <= 0.0 || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        0.0
+    } else if x < a + 1.0 {
+        gamma_series(a, x)
+    } else {
+        1.0 - gamma_continued_fraction(a, x)
+    }
+}
+
+/// The regularized upper incomplete gamma function `Q(a, x) = 1 - P(a, x)`.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::special::gamma_q;
+///
+/// // Q(1/2, x) = erfc(√x).
+/// assert!((gamma_q(0.5, 1.0) - 0.15729920705028513).abs() < 1e-14);
+/// // The upper tail keeps its relative accuracy.
+/// let tail = 7.121750862815577e-6;
+/// assert!((gamma_q(10.0, 30.0) - tail).abs() < 1e-12 * tail);
+/// ```
+pub fn gamma_q(a: f64, x: f64) -> f64 {
+    if x.is_nan() || a.is_nan() || a <= 0.0 || x < 0.0 {
+        return f64::NAN;
+    }
+    if x == 0.0 {
+        1.0
+    } else if x < a + 1.0 {
+        1.0 - gamma_series(a, x)
+    } else {
+        gamma_continued_fraction(a, x)
+    }
+}
+
+fn gamma_series(a: f64, x: f64) -> f64 {
+    let mut term = 1.0 / a;
+    let mut sum = term;
+    let mut ap = a;
+    for _ in 0..10_000 {
+        ap += 1.0;
+        term *= x / ap;
+        sum += term;
+        if term.abs() < sum.abs() * f64::EPSILON {
+            break;
+        }
+    }
+    sum * (-x + a * x.ln() - ln_gamma(a)).exp()
+}
+
+fn gamma_continued_fraction(a: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let mut b = x + 1.0 - a;
+    let mut c = 1.0 / TINY;
+    let mut d = 1.0 / b;
+    let mut h = d;
+    for i in 1..10_000 {
+        let an = -(i as f64) * (i as f64 - a);
+        b += 2.0;
+        d = an * d + b;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = b + an / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < f64::EPSILON {
+            break;
+        }
+    }
+    (-x + a * x.ln() - ln_gamma(a)).exp() * h
+}
+
+/// The natural logarithm of the beta function `B(a, b)`.
+pub fn ln_beta(a: f64, b: f64) -> f64 {
+    ln_gamma(a) + ln_gamma(b) - ln_gamma(a + b)
+}
+
+/// The regularized incomplete beta function `I_x(a, b)`.
+///
+/// Evaluates the continued fraction with the modified Lentz method, using
+/// the symmetry `I_x(a, b) = 1 - I_{1-x}(b, a)` to stay where it converges
+/// quickly.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::special::beta_inc;
+///
+/// // I_0.4(2, 3) = 0.5248 exactly; the second value is from a 30-digit reference.
+/// assert!((beta_inc(2.0, 3.0, 0.4) - 0.5248).abs() < 1e-14);
+/// assert!((beta_inc(0.5, 3.5, 0.2) - 0.7725471819402369).abs() < 1e-13);
+/// assert_eq!(beta_inc(2.0, 3.0, 0.0), 0.0);
+/// assert_eq!(beta_inc(2.0, 3.0, 1.0), 1.0);
+/// ```
+pub fn beta_inc(a: f64, b: f64, x: f64) -> f64 {
+    if x.is_nan() || a.is_nan() || b.is_nan() || a <= 0.0 || b <= 0.0 {
+        return f64::NAN;
+    }
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (a * x.ln() + b * (-x).ln_1p() - ln_beta(a, b)).exp();
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let qab = a + b;
+    let qap = a + 1.0;
+    let qam = a - 1.0;
+    let mut c = 1.0;
+    let mut d = 1.0 - qab * x / qap;
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+    for m in 1..100_000 {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+        let aa = m * (b - m) * x / ((qam + m2) * (a + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        h *= d * c;
+        let aa = -(a + m) * (qab + m) * x / ((a + m2) * (qap + m2));
+        d = 1.0 + aa * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + aa / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        h *= delta;
+        if (delta - 1.0).abs() < f64::EPSILON {
+            break;
+        }
+    }
+    h
+}
@@ -0,0 +1,140 @@
+//! Sample ratio mismatch (SRM) checks.
+//!
+//! When an experiment is meant to split traffic in fixed proportions, the
+//! observed arm sizes should match those proportions up to chance. A split
+//! that is too far off points to a bug in assignment, logging or filtering,
+//! and any difference the experiment then shows may be caused by that bug
+//! rather than by the treatment. The conversion tests run this check
+//! automatically, as set by [`TestOptions::srm`].
+//!
+//! [`TestOptions::srm`]: crate::conversion::TestOptions::srm
+
+use crate::conversion::TestOptions;
+use crate::distributions::chi_squared;
+use crate::error::{Error, Result};
+
+/// What the conversion tests do about a sample ratio mismatch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SrmPolicy {
+    /// Skip the check.
+    Ignore,
+    /// Run the check and report it alongside the result.
+    #[default]
+    Flag,
+    /// Run the check and return [`Error::SampleRatioMismatch`] instead of a
+    /// result when it fails.
+    Block,
+}
+
+/// The outcome of [`srm_test`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SrmResult {
+    /// Pearson's chi-squared goodness-of-fit statistic.
+    pub statistic: f64,
+    /// The degrees of freedom, one less than the number of arms.
+    pub df: f64,
+    /// The p-value of the statistic.
+    pub p_value: f64,
+    /// Whether the p-value falls below the threshold, so the split is
+    /// implausible under the expected allocation.
+    pub mismatch: bool,
+}
+
+/// Tests whether the observed arm sizes are consistent with the expected
+/// allocation weights.
+///
+/// SRM checks conventionally use a strict threshold such as `0.001`, since
+/// they run on every experiment and a false alarm discards a valid result.
+///
+/// # Arguments
+///
+/// * `counts` - The number of units in each arm.
+/// * `weights` - The intended share of traffic of each arm, in any units;
+///   `[1.0, 1.0]` is an even split.
+/// * `alpha` - The threshold below which the p-value signals a mismatch.
+///
+/// # Errors
+///
+/// Returns [`Error::TooFewGroups`] for fewer than two arms,
+/// [`Error::InsufficientSample`] if there are no units at all, and
+/// [`Error::InvalidParameter`] if the lengths differ, a weight is not
+/// positive, or `alpha` is outside `(0, 1)`.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::srm::srm_test;
+///
+/// let result = srm_test(&[1000, 800], &[1.0, 1.0], 0.001).unwrap();
+/// assert!(result.mismatch);
+/// let result = srm_test(&[5030, 4970, 10020], &[1.0, 1.0, 2.0], 0.001).unwrap();
+/// assert!(!result.mismatch);
+/// ```
+pub fn srm_test(counts: &[u64], weights: &[f64], alpha: f64) -> Result<SrmResult> {
+    if counts.len() < 2 {
+        return Err(Error::TooFewGroups {
+            required: 2,
+            actual: counts.len(),
+        });
+    }
+    if weights.len() != counts.len() {
+        return Err(Error::InvalidParameter {
+            name: "weights",
+            value: weights.len() as f64,
+        });
+    }
+    if let Some(&weight) = weights.iter().find(|w| !(**w > 0.0 && w.is_finite())) {
+        return Err(Error::InvalidParameter {
+            name: "weights",
+            value: weight,
+        });
+    }
+    if !(alpha > 0.0 && alpha < 1.0) {
+        return Err(Error::InvalidParameter {
+            name: "alpha",
+            value: alpha,
+        });
+    }
+    let total: f64 = counts.iter().map(|&c| c as f64).sum();
+    if total == 0.0 {
+        return Err(Error::InsufficientSample {
+            required: 1,
+            actual: 0,
+        });
+    }
+    let weight_total: f64 = weights.iter().sum();
+    let statistic = counts
+        .iter()
+        .zip(weights)
+        .map(|(&observed, &weight)| {
+            let expected = total * weight / weight_total;
+            (observed as f64 - expected).powi(2) / expected
+        })
+        .sum();
+    let df = (counts.len() - 1) as f64;
+    let p_value = chi_squared::sf(statistic, df);
+    Ok(SrmResult {
+        statistic,
+        df,
+        p_value,
+        mismatch: p_value < alpha,
+    })
+}
+
+/// Runs the check configured in `options` on arms whose first entry is the
+/// control.
+pub(crate) fn check(counts: &[u64], options: &TestOptions) -> Result<Option<SrmResult>> {
+    if options.srm == SrmPolicy::Ignore {
+        return Ok(None);
+    }
+    let weights: Vec<f64> = (0..counts.len())
+        .map(|i| if i == 0 { 1.0 } else { options.allocation })
+        .collect();
+    let result = srm_test(counts, &weights, options.srm_alpha)?;
+    if result.mismatch && options.srm == SrmPolicy::Block {
+        return Err(Error::SampleRatioMismatch {
+            p_value: result.p_value,
+        });
+    }
+    Ok(Some(result))
+}
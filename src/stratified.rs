@@ -0,0 +1,323 @@
+//! Stratified analysis of a conversion experiment across segments.
+//!
+//! When the traffic split or the baseline rate differs between segments such
+//! as platforms or countries, pooling every segment into one table can
+//! produce a difference that no segment shows, or even reverse it (Simpson's
+//! paradox). The Mantel-Haenszel methods here compare the variants within
+//! each stratum and then combine the comparisons, and the Breslow-Day test
+//! asks whether the strata agree closely enough for a single combined effect
+//! to make sense.
+
+use crate::conversion::{Alternative, Arm, TestOptions, Variant};
+use crate::distributions::{chi_squared, normal};
+use crate::error::{Error, Result};
+use crate::multi_arm::OmnibusTest;
+
+/// The conversions of both variants within one stratum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Stratum {
+    /// The conversions of version A.
+    pub a: Arm,
+    /// The conversions of version B.
+    pub b: Arm,
+}
+
+impl Stratum {
+    /// Creates a stratum from the arms of both variants.
+    pub fn new(a: Arm, b: Arm) -> Self {
+        Stratum { a, b }
+    }
+}
+
+/// The outcome of [`stratified_test`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StratifiedResult {
+    /// The variant with the higher conversion rate within strata, or `None`
+    /// when the difference is not statistically significant.
+    pub winner: Option<Variant>,
+    /// The Cochran-Mantel-Haenszel statistic as a z score, positive when B
+    /// converts better.
+    pub z: f64,
+    /// The p-value under the alternative in the test options.
+    pub p_value: f64,
+    /// The one-sided p-value for the alternative that B converts better.
+    pub p_value_greater: f64,
+    /// The one-sided p-value for the alternative that A converts better.
+    pub p_value_less: f64,
+    /// The Mantel-Haenszel common risk difference, `p2 - p1` within strata.
+    pub risk_difference: f64,
+    /// Lower bound of the confidence interval for `risk_difference`.
+    pub risk_difference_ci_low: f64,
+    /// Upper bound of the confidence interval for `risk_difference`.
+    pub risk_difference_ci_high: f64,
+    /// The Mantel-Haenszel common odds ratio of B against A.
+    pub odds_ratio: f64,
+    /// Lower bound of the confidence interval for `odds_ratio`.
+    pub odds_ratio_ci_low: f64,
+    /// Upper bound of the confidence interval for `odds_ratio`.
+    pub odds_ratio_ci_high: f64,
+    /// The difference `p2 - p1` after pooling every stratum into one table,
+    /// for comparison with `risk_difference`.
+    pub crude_difference: f64,
+    /// The Breslow-Day test of a common odds ratio across strata, if at
+    /// least two strata have both conversions and non-conversions.
+    pub breslow_day: Option<OmnibusTest>,
+}
+
+/// Compares two variants within strata and combines the comparisons.
+///
+/// The test is the Cochran-Mantel-Haenszel test without continuity
+/// correction. The common odds ratio has the Robins-Breslow-Greenland
+/// confidence interval and the common risk difference Sato's. The strata
+/// need not share a traffic split or a baseline rate. `options.interval`,
+/// `options.mid_p` and the sample ratio mismatch settings do not apply.
+///
+/// # Errors
+///
+/// Returns [`Error::TooFewGroups`] if there are no strata,
+/// [`Error::SuccessesExceedTrials`] for an inconsistent arm,
+/// [`Error::InsufficientSample`] if an arm has no trials,
+/// [`Error::InvalidParameter`] for invalid options,
+/// [`Error::Overflow`] if the pooled counts overflow, and
+/// [`Error::ZeroVariance`] if no stratum has both conversions and
+/// non-conversions.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::{Arm, TestOptions};
+/// use statistical_computing::stratified::{stratified_test, Stratum};
+///
+/// // B gets most of the mobile traffic, which converts worse, so pooling
+/// // makes B look worse even though it wins on both platforms.
+/// let strata = [
+///     Stratum::new(Arm::new(300, 1000), Arm::new(66, 200)),
+///     Stratum::new(Arm::new(20, 200), Arm::new(120, 1000)),
+/// ];
+/// let result = stratified_test(&strata, &TestOptions::default()).unwrap();
+/// assert!(result.crude_difference < 0.0);
+/// assert!(result.risk_difference > 0.0);
+/// ```
+///
+/// On the Berkeley admissions data, comparing men (B) with women (A)
+/// within departments, the test and odds ratio match R's
+/// `mantelhaen.test(UCBAdmissions, correct = FALSE)` and the Breslow-Day
+/// test matches `DescTools::BreslowDayTest`:
+///
+/// ```
+/// use statistical_computing::conversion::{Arm, TestOptions};
+/// use statistical_computing::stratified::{stratified_test, Stratum};
+///
+/// // (admitted, rejected) for men, then women, in departments A to F.
+/// let departments = [
+///     ((512, 313), (89, 19)),
+///     ((353, 207), (17, 8)),
+///     ((120, 205), (202, 391)),
+///     ((138, 279), (131, 244)),
+///     ((53, 138), (94, 299)),
+///     ((22, 351), (24, 317)),
+/// ];
+/// let strata: Vec<Stratum> = departments
+///     .iter()
+///     .map(|&((ma, mr), (fa, fr))| Stratum::new(Arm::new(fa, fa + fr), Arm::new(ma, ma + mr)))
+///     .collect();
+/// let result = stratified_test(&strata, &TestOptions::default()).unwrap();
+///
+/// // Mantel-Haenszel X-squared = 1.5246, p-value = 0.2169.
+/// assert!((result.z * result.z - 1.5246).abs() < 1e-4);
+/// assert!((result.p_value - 0.2169).abs() < 1e-4);
+/// // Common odds ratio 0.9046968, 95% CI 0.7719074 to 1.0603298.
+/// assert!((result.odds_ratio - 0.9046968).abs() < 1e-7);
+/// assert!((result.odds_ratio_ci_low - 0.7719074).abs() < 1e-7);
+/// assert!((result.odds_ratio_ci_high - 1.0603298).abs() < 1e-7);
+/// // Breslow-Day X-squared = 18.826, df = 5, p-value = 0.002072.
+/// let breslow_day = result.breslow_day.unwrap();
+/// assert!((breslow_day.statistic - 18.826).abs() < 1e-3);
+/// assert_eq!(breslow_day.df, 5.0);
+/// assert!((breslow_day.p_value - 0.002072).abs() < 1e-6);
+/// // Common risk difference -0.018425 with Sato's 95% CI -0.047482 to 0.010632,
+/// // from the formulas of Sato (1989) worked by hand.
+/// assert!((result.risk_difference + 0.018425).abs() < 1e-6);
+/// assert!((result.risk_difference_ci_low + 0.047482).abs() < 1e-6);
+/// assert!((result.risk_difference_ci_high - 0.010632).abs() < 1e-6);
+/// ```
+pub fn stratified_test(strata: &[Stratum], options: &TestOptions) -> Result<StratifiedResult> {
+    options.validate()?;
+    if strata.is_empty() {
+        return Err(Error::TooFewGroups {
+            required: 1,
+            actual: 0,
+        });
+    }
+    for stratum in strata {
+        for arm in [stratum.a, stratum.b] {
+            arm.validate()?;
+            if arm.trials == 0 {
+                return Err(Error::InsufficientSample {
+                    required: 1,
+                    actual: 0,
+                });
+            }
+        }
+    }
+
+    // With B as the exposed row, each stratum is the table
+    //   B: x1 of n1,  A: x0 of n0.
+    let mut deviation = 0.0;
+    let mut variance = 0.0;
+    let (mut r, mut s) = (0.0, 0.0);
+    let (mut pr, mut ps_qr, mut qs) = (0.0, 0.0, 0.0);
+    let (mut weights, mut weighted_difference) = (0.0, 0.0);
+    let (mut sato_p, mut sato_q) = (0.0, 0.0);
+    let (mut crude_a, mut crude_b) = (Arm::default(), Arm::default());
+    let add = |total: Arm, arm: Arm| -> Result<Arm> {
+        Ok(Arm::new(
+            total
+                .successes
+                .checked_add(arm.successes)
+                .ok_or(Error::Overflow)?,
+            total
+                .trials
+                .checked_add(arm.trials)
+                .ok_or(Error::Overflow)?,
+        ))
+    };
+    for stratum in strata {
+        let (x1, n1) = (stratum.b.successes as f64, stratum.b.trials as f64);
+        let (x0, n0) = (stratum.a.successes as f64, stratum.a.trials as f64);
+        let n = n1 + n0;
+        let m1 = x1 + x0;
+        let m0 = n - m1;
+
+        deviation += x1 - n1 * m1 / n;
+        if n > 1.0 {
+            variance += n1 * n0 * m1 * m0 / (n * n * (n - 1.0));
+        }
+
+        let (a, b, c, d) = (x1, n1 - x1, x0, n0 - x0);
+        let (rk, sk) = (a * d / n, b * c / n);
+        let (pk, qk) = ((a + d) / n, (b + c) / n);
+        r += rk;
+        s += sk;
+        pr += pk * rk;
+        ps_qr += pk * sk + qk * rk;
+        qs += qk * sk;
+
+        let w = n1 * n0 / n;
+        weights += w;
+        weighted_difference += (x1 * n0 - x0 * n1) / n;
+        sato_p += (n1 * n1 * x0 - n0 * n0 * x1 + n1 * n0 * (n0 - n1) / 2.0) / (n * n);
+        sato_q += (x1 * (n0 - x0) + x0 * (n1 - x1)) / (2.0 * n);
+
+        crude_a = add(crude_a, stratum.a)?;
+        crude_b = add(crude_b, stratum.b)?;
+    }
+    if variance <= 0.0 {
+        return Err(Error::ZeroVariance);
+    }
+
+    let z = deviation / variance.sqrt();
+    let risk_difference = weighted_difference / weights;
+    let risk_difference_error = ((risk_difference * sato_p + sato_q).max(0.0)).sqrt() / weights;
+    let (risk_difference_ci_low, risk_difference_ci_high) =
+        bounds(risk_difference, risk_difference_error, options);
+    let (risk_difference_ci_low, risk_difference_ci_high) = (
+        risk_difference_ci_low.max(-1.0),
+        risk_difference_ci_high.min(1.0),
+    );
+
+    let odds_ratio = r / s;
+    let (odds_ratio_ci_low, odds_ratio_ci_high) = if r > 0.0 && s > 0.0 {
+        let log_error = (pr / (2.0 * r * r) + ps_qr / (2.0 * r * s) + qs / (2.0 * s * s)).sqrt();
+        let (low, high) = bounds(odds_ratio.ln(), log_error, options);
+        (low.exp(), high.exp())
+    } else {
+        (0.0, f64::INFINITY)
+    };
+
+    Ok(StratifiedResult {
+        winner: options.alternative.winner(z, options.alpha),
+        z,
+        p_value: options.alternative.p_value(z),
+        p_value_greater: Alternative::Greater.p_value(z),
+        p_value_less: Alternative::Less.p_value(z),
+        risk_difference,
+        risk_difference_ci_low,
+        risk_difference_ci_high,
+        odds_ratio,
+        odds_ratio_ci_low,
+        odds_ratio_ci_high,
+        crude_difference: crude_b.rate() - crude_a.rate(),
+        breslow_day: breslow_day(strata, odds_ratio),
+    })
+}
+
+/// A normal confidence interval for `estimate`, one-sided for one-sided
+/// alternatives.
+fn bounds(estimate: f64, error: f64, options: &TestOptions) -> (f64, f64) {
+    match options.alternative {
+        Alternative::TwoSided => {
+            let m = normal::quantile((1.0 + options.confidence) / 2.0) * error;
+            (estimate - m, estimate + m)
+        }
+        Alternative::Greater => (
+            estimate - normal::quantile(options.confidence) * error,
+            f64::INFINITY,
+        ),
+        Alternative::Less => (
+            f64::NEG_INFINITY,
+            estimate + normal::quantile(options.confidence) * error,
+        ),
+    }
+}
+
+/// The Breslow-Day test that every stratum shares the odds ratio `psi`.
+fn breslow_day(strata: &[Stratum], psi: f64) -> Option<OmnibusTest> {
+    if !(psi > 0.0 && psi.is_finite()) {
+        return None;
+    }
+    let mut statistic = 0.0;
+    let mut informative = 0;
+    for stratum in strata {
+        let (x1, n1) = (stratum.b.successes as f64, stratum.b.trials as f64);
+        let (x0, n0) = (stratum.a.successes as f64, stratum.a.trials as f64);
+        let m1 = x1 + x0;
+        let low = (m1 - n0).max(0.0);
+        let high = n1.min(m1);
+        if high <= low {
+            continue;
+        }
+        // The count of B's conversions whose table has odds ratio psi solves
+        // e (n0 - m1 + e) = psi (n1 - e) (m1 - e).
+        let qa = 1.0 - psi;
+        let qb = n0 - m1 + psi * (n1 + m1);
+        let qc = -psi * n1 * m1;
+        let expected = if qa.abs() < 1e-12 {
+            -qc / qb
+        } else {
+            let root = (qb * qb - 4.0 * qa * qc).max(0.0).sqrt();
+            let first = (-qb + root) / (2.0 * qa);
+            let second = (-qb - root) / (2.0 * qa);
+            if first >= low && first <= high {
+                first
+            } else {
+                second
+            }
+        };
+        let cells = [expected, n1 - expected, m1 - expected, n0 - m1 + expected];
+        let variance = 1.0 / cells.iter().map(|c| 1.0 / c).sum::<f64>();
+        if variance > 0.0 && variance.is_finite() {
+            statistic += (x1 - expected).powi(2) / variance;
+            informative += 1;
+        }
+    }
+    if informative < 2 {
+        return None;
+    }
+    let df = (informative - 1) as f64;
+    Some(OmnibusTest {
+        statistic,
+        df,
+        p_value: chi_squared::sf(statistic, df),
+    })
+}
@@ -0,0 +1,304 @@
+//! Two-sample tests for continuous metrics such as revenue per user.
+//!
+//! The tests compare the means of version A and version B from summary
+//! statistics, so they work equally on raw observations and on figures
+//! exported from a warehouse. Results follow [`ConversionTestResult`], with
+//! the difference of means `mean2 - mean1` in place of the difference of
+//! rates.
+//!
+//! These tests, and the rank, ratio and CUPED tests alongside them, take
+//! [`ContinuousOptions`]: the settings of [`TestOptions`] that carry over to
+//! a continuous metric, without the interval methods and traffic split
+//! check that only concern conversion counts.
+//!
+//! [`ConversionTestResult`]: crate::conversion::ConversionTestResult
+
+use crate::conversion::{Alternative, TestOptions, Variant};
+use crate::distributions::student_t;
+use crate::error::{Error, Result};
+
+/// Settings shared by the tests on continuous metrics.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::conversion::{Alternative, TestOptions};
+/// use statistical_computing::two_sample::ContinuousOptions;
+///
+/// let options = ContinuousOptions::default().alternative(Alternative::Greater);
+/// assert_eq!(options.alpha, 0.05);
+///
+/// // The matching settings of a conversion test carry over.
+/// let options = ContinuousOptions::from(TestOptions::default().alpha(0.01));
+/// assert_eq!(options.alpha, 0.01);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContinuousOptions {
+    /// The significance level at which the null hypothesis is rejected.
+    pub alpha: f64,
+    /// The coverage of the reported confidence interval.
+    pub confidence: f64,
+    /// The alternative hypothesis. One-sided alternatives report the matching
+    /// one-sided confidence bound.
+    pub alternative: Alternative,
+}
+
+impl Default for ContinuousOptions {
+    fn default() -> Self {
+        ContinuousOptions {
+            alpha: 0.05,
+            confidence: 0.95,
+            alternative: Alternative::TwoSided,
+        }
+    }
+}
+
+impl From<TestOptions> for ContinuousOptions {
+    fn from(options: TestOptions) -> Self {
+        ContinuousOptions {
+            alpha: options.alpha,
+            confidence: options.confidence,
+            alternative: options.alternative,
+        }
+    }
+}
+
+impl ContinuousOptions {
+    /// Sets the significance level.
+    pub fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the confidence level of the reported interval.
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    /// Sets the alternative hypothesis.
+    pub fn alternative(mut self, alternative: Alternative) -> Self {
+        self.alternative = alternative;
+        self
+    }
+
+    /// Checks that the levels lie strictly between 0 and 1.
+    pub fn validate(&self) -> Result<()> {
+        for (name, value) in [("alpha", self.alpha), ("confidence", self.confidence)] {
+            if !(value > 0.0 && value < 1.0) {
+                return Err(Error::InvalidParameter { name, value });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The size, mean and sample variance of one arm's observations.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Summary {
+    /// The number of observations.
+    pub n: u64,
+    /// The sample mean.
+    pub mean: f64,
+    /// The unbiased sample variance, with denominator `n - 1`.
+    pub variance: f64,
+}
+
+impl Summary {
+    /// Creates a summary from its statistics.
+    pub fn new(n: u64, mean: f64, variance: f64) -> Self {
+        Summary { n, mean, variance }
+    }
+
+    /// Summarises raw observations with Welford's algorithm. The variance is
+    /// zero when there are fewer than two values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use statistical_computing::two_sample::Summary;
+    ///
+    /// let summary = Summary::from_values(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]);
+    /// assert_eq!(summary.mean, 5.0);
+    /// assert!((summary.variance - 32.0 / 7.0).abs() < 1e-12);
+    /// ```
+    pub fn from_values(values: &[f64]) -> Self {
+        let mut mean = 0.0;
+        let mut squares = 0.0;
+        for (i, &x) in values.iter().enumerate() {
+            let delta = x - mean;
+            mean += delta / (i + 1) as f64;
+            squares += delta * (x - mean);
+        }
+        let n = values.len() as u64;
+        let variance = if n > 1 { squares / (n - 1) as f64 } else { 0.0 };
+        Summary { n, mean, variance }
+    }
+
+    /// Checks that there are at least two observations and that the mean and
+    /// variance are finite, with a variance that is not negative.
+    pub fn validate(&self) -> Result<()> {
+        if self.n < 2 {
+            return Err(Error::InsufficientSample {
+                required: 2,
+                actual: self.n,
+            });
+        }
+        if !self.mean.is_finite() {
+            return Err(Error::InvalidParameter {
+                name: "mean",
+                value: self.mean,
+            });
+        }
+        if !(self.variance >= 0.0 && self.variance.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "variance",
+                value: self.variance,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of a two-sample t-test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TTestResult {
+    /// The variant with the higher mean, or `None` when the difference is not
+    /// statistically significant.
+    pub winner: Option<Variant>,
+    /// The t statistic, signed like `difference`.
+    pub t: f64,
+    /// The degrees of freedom of the t distribution the statistic is
+    /// compared with.
+    pub df: f64,
+    /// The p-value under the alternative in the test options.
+    pub p_value: f64,
+    /// The one-sided p-value for the alternative that B has the higher mean.
+    pub p_value_greater: f64,
+    /// The one-sided p-value for the alternative that A has the higher mean.
+    pub p_value_less: f64,
+    /// The difference of means, `mean2 - mean1`.
+    pub difference: f64,
+    /// Lower bound of the confidence interval for `difference`; `-inf` for a
+    /// [`Alternative::Less`] test.
+    pub ci_low: f64,
+    /// Upper bound of the confidence interval for `difference`; `inf` for a
+    /// [`Alternative::Greater`] test.
+    pub ci_high: f64,
+    /// The standard error of `difference`.
+    pub standard_error: f64,
+}
+
+/// Student's t-test, which assumes both arms share a variance and pools it.
+///
+/// Prefer [`welch_t_test`] unless the arms are known to have equal variances;
+/// Student's test is badly calibrated when they differ and the arm sizes are
+/// unequal.
+///
+/// # Errors
+///
+/// Returns [`Error::InsufficientSample`] if an arm has fewer than two
+/// observations, [`Error::ZeroVariance`] if both arms are constant, and
+/// [`Error::InvalidParameter`] for invalid options or statistics.
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::two_sample::{student_t_test, ContinuousOptions, Summary};
+///
+/// let a = Summary::new(40, 10.0, 4.0);
+/// let b = Summary::new(40, 11.5, 4.0);
+/// let result = student_t_test(a, b, &ContinuousOptions::default()).unwrap();
+/// assert_eq!(result.df, 78.0);
+/// assert!(result.p_value < 0.01);
+/// ```
+pub fn student_t_test(a: Summary, b: Summary, options: &ContinuousOptions) -> Result<TTestResult> {
+    check(a, b, options)?;
+    let (na, nb) = (a.n as f64, b.n as f64);
+    let df = na + nb - 2.0;
+    let pooled = ((na - 1.0) * a.variance + (nb - 1.0) * b.variance) / df;
+    let standard_error = (pooled * (1.0 / na + 1.0 / nb)).sqrt();
+    t_test(b.mean - a.mean, standard_error, df, options)
+}
+
+/// Welch's t-test, which allows the arms to have different variances.
+///
+/// The degrees of freedom come from the Welch-Satterthwaite approximation.
+///
+/// # Errors
+///
+/// Returns the same errors as [`student_t_test`].
+///
+/// # Example
+///
+/// ```
+/// use statistical_computing::two_sample::{welch_t_test, ContinuousOptions, Summary};
+///
+/// let a = Summary::from_values(&[19.1, 21.0, 20.4, 18.7, 22.3, 20.9]);
+/// let b = Summary::from_values(&[23.5, 19.8, 27.1, 25.0, 21.9, 30.2, 24.4]);
+/// let result = welch_t_test(a, b, &ContinuousOptions::default()).unwrap();
+/// assert!(result.df < 11.0);
+/// assert!(result.ci_low > 0.0);
+/// ```
+pub fn welch_t_test(a: Summary, b: Summary, options: &ContinuousOptions) -> Result<TTestResult> {
+    check(a, b, options)?;
+    let va = a.variance / a.n as f64;
+    let vb = b.variance / b.n as f64;
+    let df = (va + vb).powi(2) / (va * va / (a.n - 1) as f64 + vb * vb / (b.n - 1) as f64);
+    t_test(b.mean - a.mean, (va + vb).sqrt(), df, options)
+}
+
+fn check(a: Summary, b: Summary, options: &ContinuousOptions) -> Result<()> {
+    options.validate()?;
+    a.validate()?;
+    b.validate()?;
+    if a.variance == 0.0 && b.variance == 0.0 {
+        return Err(Error::ZeroVariance);
+    }
+    Ok(())
+}
+
+fn t_test(
+    difference: f64,
+    standard_error: f64,
+    df: f64,
+    options: &ContinuousOptions,
+) -> Result<TTestResult> {
+    let t = difference / standard_error;
+    let p_value_greater = student_t::sf(t, df);
+    let p_value_less = student_t::cdf(t, df);
+    let p_value = match options.alternative {
+        Alternative::TwoSided => (2.0 * p_value_greater.min(p_value_less)).min(1.0),
+        Alternative::Greater => p_value_greater,
+        Alternative::Less => p_value_less,
+    };
+    let margin = |level: f64| student_t::quantile(level, df) * standard_error;
+    let (ci_low, ci_high) = match options.alternative {
+        Alternative::TwoSided => {
+            let m = margin((1.0 + options.confidence) / 2.0);
+            (difference - m, difference + m)
+        }
+        Alternative::Greater => (difference - margin(options.confidence), f64::INFINITY),
+        Alternative::Less => (f64::NEG_INFINITY, difference + margin(options.confidence)),
+    };
+    let winner = if p_value >= options.alpha {
+        None
+    } else if t > 0.0 {
+        Some(Variant::B)
+    } else {
+        Some(Variant::A)
+    };
+
+    Ok(TTestResult {
+        winner,
+        t,
+        df,
+        p_value,
+        p_value_greater,
+        p_value_less,
+        difference,
+        ci_low,
+        ci_high,
+        standard_error,
+    })
+}